@@ -1,3 +1,27 @@
 mod add;
+mod check;
+mod dedup;
+mod export;
+mod init;
+mod list;
+mod r#move;
+mod prune;
+mod rename;
+mod search;
+mod today;
+mod undo;
+mod verify;
 
 pub use self::add::*;
+pub use self::check::*;
+pub use self::dedup::*;
+pub use self::export::*;
+pub use self::init::*;
+pub use self::list::*;
+pub use self::prune::*;
+pub use self::r#move::*;
+pub use self::rename::*;
+pub use self::search::*;
+pub use self::today::*;
+pub use self::undo::*;
+pub use self::verify::*;
@@ -1,301 +1,2680 @@
 use crate::utils::{
-    DiaryFileId, DiaryRepository, FoundSourceFile, SourceFile, SourceFileType, SourceRepository,
+    add_note_tags, add_sidecar_tags, auto_orient, extract_gps, find_note_links, format_size,
+    generate_thumbnail, get_burst_name, get_media_label, get_media_name, hash_file, move_file,
+    normalize_ext, parse_ext_alias, parse_size, parse_tag, parse_time_shift, relative_path,
+    rewrite_note_links, strip_metadata, AddMode, DateFilter, DateSource, DiaryArgs, DiaryFileId,
+    DiaryRepository, FileKind, FoundSourceFile, JournalAction, JournalEntry, Layout,
+    ManifestFormat, MissingTimestamp, NameTemplate, OnConflict, SourceFile, SourceFileType,
+    SourceRepository, TimeShift, TimestampSource, BURSTS_DIR, EVENT_DIR_PREFIX,
 };
-use crate::Env;
-use anyhow::{Context, Result};
-use chrono::{NaiveDate, NaiveDateTime, Timelike};
-use clap::Parser;
+use crate::{Config, Env, Verbosity};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use clap::{Parser, ValueEnum};
 use colored::Colorize;
+use glob::Pattern;
+use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
-use std::path::PathBuf;
+use serde::Serialize;
+use serde_json::json;
+use std::cell::Cell;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use std::{fs, iter};
+use tracing::{debug, info_span};
 
 #[derive(Debug, Parser)]
 pub struct AddCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["diary", "layout", "notes_subdir", "media_subdir"])]
+    flatten: Option<PathBuf>,
+
+    #[clap(long)]
+    source: Vec<PathBuf>,
+
+    #[clap(long)]
+    #[clap(value_parser = Pattern::new)]
+    exclude: Vec<Pattern>,
+
+    #[clap(long)]
+    follow_symlinks: bool,
+
+    #[clap(long)]
+    include_hidden: bool,
+
+    #[clap(long)]
+    quarantine: Option<PathBuf>,
+
+    #[clap(long, conflicts_with = "journal")]
+    trash_dir: Option<PathBuf>,
+
+    #[clap(long)]
+    exif_cache: Option<PathBuf>,
+
+    #[clap(long)]
+    require_exiftool: bool,
+
+    #[clap(long)]
+    jobs: Option<usize>,
+
+    #[clap(long)]
+    #[clap(value_enum, default_value_t = TimestampSource::Min)]
+    timestamp_source: TimestampSource,
+
+    #[clap(long)]
+    #[clap(value_enum, default_value_t = MissingTimestamp::Skip)]
+    on_missing_timestamp: MissingTimestamp,
+
+    #[clap(long)]
+    #[clap(default_value = "2000-01-01")]
+    min_date: NaiveDate,
+
+    #[clap(long, allow_hyphen_values = true)]
+    #[clap(value_parser = parse_time_shift)]
+    time_shift: Option<TimeDelta>,
+
+    #[clap(long, requires = "time_shift")]
+    time_shift_from: Option<NaiveDate>,
+
+    #[clap(long, requires = "time_shift")]
+    time_shift_to: Option<NaiveDate>,
+
+    #[clap(long)]
+    max_date: Option<NaiveDate>,
+
+    #[clap(flatten)]
+    filter: DateFilter,
+
     #[clap(long)]
-    diary: PathBuf,
+    #[clap(value_parser = parse_time)]
+    after_time: Option<NaiveTime>,
 
     #[clap(long)]
-    source: PathBuf,
+    #[clap(value_parser = parse_time)]
+    before_time: Option<NaiveTime>,
+
+    #[clap(long = "type")]
+    #[clap(value_delimiter = ',')]
+    types: Vec<FileKind>,
 
     #[clap(long)]
-    on: Option<NaiveDate>,
+    #[clap(value_enum, default_value_t = SortBy::Name)]
+    sort: SortBy,
 
     #[clap(long)]
-    #[clap(conflicts_with = "on")]
-    from: Option<NaiveDate>,
+    reverse: bool,
 
     #[clap(long)]
-    #[clap(conflicts_with = "on")]
-    #[clap(requires = "from")]
-    to: Option<NaiveDate>,
+    #[clap(value_parser = parse_size)]
+    max_size: Option<u64>,
 
     #[clap(long)]
     remove: bool,
 
+    #[clap(long)]
+    #[clap(conflicts_with = "remove")]
+    no_remove: bool,
+
+    #[clap(long = "move")]
+    #[clap(conflicts_with = "remove")]
+    r#move: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["remove", "move"])]
+    symlink: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["remove", "move", "symlink"])]
+    hardlink: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["symlink", "hardlink"])]
+    convert_heic: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["symlink", "hardlink"])]
+    strip_exif: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["symlink", "hardlink"])]
+    auto_orient: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["symlink", "hardlink"])]
+    copy_note_assets: bool,
+
+    #[clap(long)]
+    thumbnails: bool,
+
+    #[clap(long)]
+    #[clap(default_value_t = 256)]
+    thumbnail_size: u32,
+
+    #[clap(long)]
+    live_photos: bool,
+
+    #[clap(long)]
+    since_last_run: bool,
+
+    #[clap(long, conflicts_with = "flatten")]
+    link_latest: bool,
+
+    #[clap(long, conflicts_with = "flatten")]
+    journal: bool,
+
+    #[clap(long)]
+    hash_manifest: Option<PathBuf>,
+
+    #[clap(long, requires = "hash_manifest")]
+    #[clap(value_enum, default_value_t = ManifestFormat::Blake3)]
+    manifest_format: ManifestFormat,
+
+    #[clap(long)]
+    report: Option<PathBuf>,
+
+    #[clap(long)]
+    geojson: Option<PathBuf>,
+
+    #[clap(long)]
+    no_preserve_time: bool,
+
+    #[clap(long)]
+    #[clap(value_parser = parse_size)]
+    copy_buffer_size: Option<u64>,
+
+    #[clap(long)]
+    name_template: Option<NameTemplate>,
+
+    #[clap(long)]
+    prefix_date: bool,
+
+    #[clap(long)]
+    no_group_bursts: bool,
+
+    #[clap(long)]
+    #[clap(conflicts_with = "flatten")]
+    keep_structure: bool,
+
+    #[clap(long, visible_alias = "group-by-event")]
+    #[clap(conflicts_with = "flatten")]
+    events: bool,
+
+    #[clap(long, value_name = "MINUTES")]
+    #[clap(default_value_t = 90)]
+    #[clap(requires = "events")]
+    event_gap: u32,
+
+    #[clap(long = "screenshot-prefix")]
+    screenshot_prefixes: Vec<String>,
+
+    #[clap(long = "screencast-prefix")]
+    screencast_prefixes: Vec<String>,
+
+    #[clap(long)]
+    normalize_ext: bool,
+
+    #[clap(long = "ext-alias", requires = "normalize_ext")]
+    #[clap(value_parser = parse_ext_alias)]
+    ext_aliases: Vec<(String, String)>,
+
+    #[clap(long)]
+    #[clap(value_enum, default_value_t = OnConflict::Skip)]
+    on_conflict: OnConflict,
+
+    #[clap(long)]
+    dedup: bool,
+
+    #[clap(long)]
+    append: bool,
+
+    #[clap(long)]
+    merge_notes: bool,
+
+    #[clap(long)]
+    with_note: bool,
+
+    #[clap(long)]
+    #[clap(default_value = "#+TITLE: {date}")]
+    note_header: String,
+
+    #[clap(long = "tag")]
+    #[clap(value_parser = parse_tag)]
+    tags: Vec<String>,
+
+    #[clap(long)]
+    require_changes: bool,
+
     #[clap(long)]
     dry_run: bool,
 
     #[clap(long)]
+    #[clap(requires = "dry_run")]
+    check_writable: bool,
+
+    #[clap(long)]
+    concurrency_safe: bool,
+
+    #[clap(long, visible_alias = "yes")]
+    confirm_remove: bool,
+
+    #[clap(short, long)]
+    #[clap(value_enum, num_args = 0..=1, require_equals = true)]
+    #[clap(default_missing_value = "plan")]
+    #[clap(conflicts_with_all = ["dry_run", "quiet", "silent"])]
+    interactive: Option<Interactive>,
+
+    #[clap(long)]
+    #[clap(value_enum, default_value_t = Format::Text)]
+    #[clap(requires = "dry_run")]
+    format: Format,
+
+    #[clap(long)]
+    #[clap(conflicts_with = "silent")]
+    quiet: bool,
+
+    #[clap(long)]
+    silent: bool,
+
+    #[clap(short, long)]
     verbose: bool,
 }
 
 impl AddCmd {
-    pub fn run(self, env: &mut Env) -> Result<()> {
+    pub fn run(mut self, env: &mut Env) -> Result<()> {
+        let started_at = Instant::now();
+
+        self.apply_config(&env.config);
+
+        if self.format == Format::Json {
+            return self.run_json(env);
+        }
+
+        if self.silent {
+            env.verbosity = Verbosity::Silent;
+        } else if self.quiet {
+            env.verbosity = Verbosity::Quiet;
+        }
+
+        if env.verbosity < Verbosity::Normal {
+            let stats = env.muted(|env| self.import(env))?;
+
+            if env.verbosity == Verbosity::Quiet {
+                self.summary(env, &stats, started_at.elapsed())?;
+            }
+
+            return self.check_changes(&stats);
+        }
+
         if self.dry_run {
             writeln!(env.stdout, "{} is active", "--dry-run".yellow())?;
             writeln!(env.stdout)?;
         }
 
+        let stats = self.import(env)?;
+
+        writeln!(env.stdout)?;
+        self.summary(env, &stats, started_at.elapsed())?;
+
+        self.check_changes(&stats)
+    }
+
+    /// Opens the diary or, with `--flatten`, the directory all files should be
+    /// put into.
+    fn open_diary(&self, config: &Config) -> Result<DiaryRepository> {
+        if let Some(dir) = &self.flatten {
+            Ok(DiaryRepository::new(dir)?.with_layout(Layout::flat()))
+        } else {
+            self.diary.open(config)
+        }
+    }
+
+    /// Name (without extension) of the note for given day - in a flattened
+    /// import, notes of all days live next to each other, so there it's the
+    /// date.
+    fn note_stem(&self, date: NaiveDate) -> String {
+        if self.flatten.is_some() {
+            date.to_string()
+        } else {
+            "index".into()
+        }
+    }
+
+    /// Fails if nothing has been imported and `--require-changes` is active.
+    fn check_changes(&self, stats: &Stats) -> Result<()> {
+        if self.require_changes && !stats.has_changes() {
+            bail!("nothing has changed, but `--require-changes` is active");
+        }
+
+        Ok(())
+    }
+
+    fn import(&self, env: &mut Env) -> Result<Stats> {
+        // Held until the import is done, including bookkeeping such as
+        // `--since-last-run` (a dry run doesn't change anything, so it doesn't
+        // need the lock)
+        let _lock = if self.concurrency_safe && !self.dry_run {
+            self.open_diary(&env.config)?.lock()?
+        } else {
+            None
+        };
+
         let srcs = self.scan(env)?;
-        let plan = self.plan(env, &srcs)?;
-        let stats = self.exec(env, plan)?;
+        let mut plan = self.plan(env, &srcs)?;
+
+        if self.interactive == Some(Interactive::Plan) && !self.dry_run && io::stdin().is_terminal()
+        {
+            self.exec(env, plan.clone(), true)?;
+
+            if !Self::confirm(env, "Proceed?")? {
+                return Ok(Stats::default());
+            }
+        } else if !self.dry_run && !self.confirm_remove {
+            Self::confirm_removals(env, &mut plan)?;
+        }
+
+        let not_writable = if self.check_writable {
+            self.check_writable(env, &plan)?
+        } else {
+            0
+        };
+
+        let mut stats = self.exec(env, plan, self.dry_run)?;
+
+        stats.failed = srcs.failed;
+        stats.not_writable = not_writable;
+        stats.too_large = srcs.too_large;
+
+        if let Some(manifest) = &self.hash_manifest {
+            if !self.dry_run {
+                self.write_manifest(env, manifest, &stats.added)?;
+            }
+        }
+
+        if let Some(geojson) = &self.geojson {
+            if !self.dry_run {
+                self.write_geojson(env, geojson, &stats.added)?;
+            }
+        }
+
+        if self.since_last_run && !self.dry_run {
+            self.open_diary(&env.config)?
+                .set_last_run(srcs.scanned_at)?;
+        }
+
+        if self.link_latest && !self.dry_run {
+            self.open_diary(&env.config)?.link_latest()?;
+        }
+
+        if let Some(report) = &self.report {
+            self.write_report(report, &stats, srcs.scanned_at)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Tries creating directories the plan adds files into, reporting ones
+    /// that aren't writable (see `--check-writable`); returns their count.
+    fn check_writable(&self, env: &mut Env, plan: &Plan) -> Result<usize> {
+        writeln!(env.stdout, "{}", "checking".green().bold())?;
+
+        let diary = self.open_diary(&env.config)?;
+
+        let dirs: BTreeSet<_> = plan
+            .steps
+            .iter()
+            .filter_map(Step::dst)
+            .filter_map(|dst| diary.rel_file(dst).parent().map(Path::to_owned))
+            .collect();
+
+        let mut failed = 0;
+
+        for dir in &dirs {
+            if let Err(err) = diary.check_writable(dir) {
+                writeln!(
+                    env.stderr,
+                    "  {} `diary:{}` is not writable: {:#}",
+                    "warn".yellow(),
+                    dir.display(),
+                    err,
+                )?;
+
+                failed += 1;
+            }
+        }
+
+        if failed == 0 {
+            writeln!(
+                env.stdout,
+                "  {} director{} writable",
+                dirs.len(),
+                if dirs.len() == 1 { "y is" } else { "ies are" },
+            )?;
+        }
+
+        writeln!(env.stdout)?;
+
+        Ok(failed)
+    }
+
+    /// Asks the user whether source files should be removed, unless that's
+    /// been already confirmed through `--confirm-remove`; without a terminal
+    /// to ask on, removals get dropped from the plan.
+    fn confirm_removals(env: &mut Env, plan: &mut Plan) -> Result<()> {
+        let count = plan
+            .steps
+            .iter()
+            .filter(|step| matches!(step, Step::Remove { .. }))
+            .count();
+
+        if count == 0 {
+            return Ok(());
+        }
+
+        let files = format!("{} source file{}", count, if count > 1 { "s" } else { "" });
+
+        if env.is_terminal
+            && io::stdin().is_terminal()
+            && Self::confirm(env, &format!("Delete {}?", files))?
+        {
+            return Ok(());
+        }
+
+        writeln!(
+            env.stderr,
+            "{} not removing {} - pass `--confirm-remove` to remove them",
+            "warn".yellow(),
+            files
+        )?;
+
+        plan.steps
+            .retain(|step| !matches!(step, Step::Remove { .. }));
+
+        Ok(())
+    }
+
+    /// Asks the user a yes/no question, e.g. whether the plan (printed just
+    /// before) should be executed.
+    fn confirm(env: &mut Env, question: &str) -> Result<bool> {
+        writeln!(env.stdout)?;
+        write!(env.stdout, "{} [y/N] ", question.yellow().bold())?;
+        env.stdout.flush()?;
+
+        let mut answer = String::new();
+
+        io::stdin()
+            .read_line(&mut answer)
+            .context("couldn't read answer")?;
+
+        writeln!(env.stdout)?;
+
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+
+    /// Appends hashes of files that have just been added into the diary to
+    /// given manifest.
+    fn write_manifest(&self, env: &Env, path: &Path, files: &[DiaryFileId]) -> Result<()> {
+        let diary = self.open_diary(&env.config)?;
+
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("couldn't open: {}", path.display()))?;
+
+        for file in files {
+            let hash = self.manifest_format.hash_file(diary.file(file))?;
+
+            // (laid out like `sha256sum` etc. do it, so that the manifest can
+            // be checked with them, too)
+            writeln!(manifest, "{}  {}", hash, diary.rel_file(file).display())
+                .with_context(|| format!("couldn't write: {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends a JSON line describing this run to given report, so that there's
+    /// a history of imports.
+    fn write_report(&self, path: &Path, stats: &Stats, at: DateTime<Local>) -> Result<()> {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            timestamp: String,
+            source: &'a [PathBuf],
+            dry_run: bool,
+            stats: &'a Stats,
+        }
+
+        let entry = serde_json::to_string(&Entry {
+            timestamp: at.to_rfc3339(),
+            source: &self.source,
+            dry_run: self.dry_run,
+            stats,
+        })?;
+
+        let mut report = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("couldn't open: {}", path.display()))?;
+
+        writeln!(report, "{}", entry)
+            .with_context(|| format!("couldn't write: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Writes a GeoJSON `FeatureCollection` of photos that have just been
+    /// added into the diary, so that they can be shown on a map.
+    ///
+    /// Photos without GPS data are omitted.
+    fn write_geojson(&self, env: &Env, path: &Path, files: &[DiaryFileId]) -> Result<()> {
+        let diary = self.open_diary(&env.config)?;
+        let mut features = Vec::new();
+
+        for file in files {
+            if file.kind() != Some(FileKind::Photo) {
+                continue;
+            }
+
+            let Some(coords) = extract_gps(&diary.file(file))? else {
+                continue;
+            };
+
+            features.push(json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [coords.lon, coords.lat],
+                },
+                "properties": {
+                    "path": file,
+                    "date": file.date,
+                },
+            }));
+        }
+
+        let geojson = json!({
+            "type": "FeatureCollection",
+            "features": features,
+        });
+
+        let geojson = serde_json::to_string_pretty(&geojson)?;
+
+        fs::write(path, format!("{}\n", geojson))
+            .with_context(|| format!("couldn't write: {}", path.display()))
+    }
+
+    /// Prints the plan as JSON, without executing it.
+    fn run_json(&self, env: &mut Env) -> Result<()> {
+        let plan = env.muted(|env| {
+            let srcs = self.scan(env)?;
+
+            self.plan(env, &srcs)
+        })?;
+
+        serde_json::to_writer_pretty(&mut env.stdout, &plan)?;
+        writeln!(env.stdout)?;
+
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        if self.source.is_empty() {
+            self.source.extend(config.source.clone());
+        }
+
+        if self.name_template.is_none() {
+            self.name_template.clone_from(&config.name_template);
+        }
+
+        if self.screenshot_prefixes.is_empty() {
+            self.screenshot_prefixes
+                .extend(config.screenshot_prefixes.iter().flatten().cloned());
+        }
+
+        if self.screencast_prefixes.is_empty() {
+            self.screencast_prefixes
+                .extend(config.screencast_prefixes.iter().flatten().cloned());
+        }
+
+        if self.ext_aliases.is_empty() {
+            self.ext_aliases.extend(
+                config
+                    .ext_aliases
+                    .iter()
+                    .flatten()
+                    .map(|(from, to)| (from.to_lowercase(), to.to_lowercase())),
+            );
+        }
+
+        if !self.remove && !self.no_remove && !self.r#move && !self.symlink && !self.hardlink {
+            self.remove = config.remove.unwrap_or(false);
+        }
+    }
+
+    fn scan(&self, env: &mut Env) -> Result<Sources> {
+        let _span = info_span!("scan").entered();
+
+        writeln!(env.stdout, "{}", "scanning".green().bold())?;
+
+        let scanned_at = Local::now();
+
+        let last_run = if self.since_last_run {
+            self.open_diary(&env.config)?.last_run()?
+        } else {
+            None
+        };
+
+        if self.source.is_empty() {
+            bail!("missing source directory - use `--source` or set it in the config");
+        }
+
+        let sources: Vec<_> = self
+            .source
+            .iter()
+            .map(|dir| {
+                let mut source = SourceRepository::new(dir)?
+                    .with_jobs(self.jobs.unwrap_or(0))
+                    .with_timestamp_source(self.timestamp_source)
+                    .with_missing_timestamp(self.on_missing_timestamp)
+                    .with_date_range(self.date_range())
+                    .with_excludes(self.exclude.clone())
+                    .with_follow_symlinks(self.follow_symlinks)
+                    .with_include_hidden(self.include_hidden)
+                    .with_require_exiftool(self.require_exiftool);
+
+                if let Some(exif_cache) = &self.exif_cache {
+                    source = source.with_exif_cache(exif_cache);
+                }
+
+                if let Some(delta) = self.time_shift {
+                    source = source.with_time_shift(TimeShift {
+                        delta,
+                        range: self.time_shift_from.unwrap_or(NaiveDate::MIN)
+                            ..=self.time_shift_to.unwrap_or(NaiveDate::MAX),
+                    });
+                }
+
+                Ok((dir, source))
+            })
+            .collect::<Result<_>>()?;
+
+        // When importing from many sources at once, say which one each file
+        // comes from, so that the output is easier to follow
+        let prefix = |dir: &Path| {
+            if sources.len() > 1 {
+                format!("[{}] ", dir.display())
+            } else {
+                String::new()
+            }
+        };
+
+        let mut unrecognized = Vec::new();
+        let mut failed = 0;
+
+        let found = sources
+            .iter()
+            .map(|(dir, source)| Ok(source.iter()?.map(move |file| (*dir, file))))
+            .collect::<Result<Vec<_>>>()?;
+
+        if sources
+            .iter()
+            .any(|(_, source)| source.is_exiftool_missing())
+        {
+            writeln!(
+                env.stderr,
+                "{} exiftool not found; using file timestamps",
+                "warn".yellow()
+            )?;
+        }
+
+        let files: Vec<_> = found
+            .into_iter()
+            .flatten()
+            .map(|(dir, file)| match file {
+                Ok(FoundSourceFile::Recognized(file)) => {
+                    if file.date_source == DateSource::CurrentTime {
+                        writeln!(
+                            env.stderr,
+                            "{} {}{}: file has no timestamps, used current time instead",
+                            "warn".yellow(),
+                            prefix(dir),
+                            file.path.display(),
+                        )?;
+                    }
+
+                    if self.verbose {
+                        let date = match file.ty.datetime() {
+                            Some(date) => date.to_string(),
+                            None => file.ty.date().to_string(),
+                        };
+
+                        writeln!(
+                            env.stdout,
+                            "  {} {}{}: {}, from {}",
+                            "found".green(),
+                            prefix(dir),
+                            file.path.display(),
+                            date,
+                            file.date_source,
+                        )?;
+
+                        if let Some(rejected_date) = file.rejected_date {
+                            writeln!(
+                                env.stderr,
+                                "  {} {}{}: date {} is out of range, used file timestamp instead",
+                                "warn".yellow(),
+                                prefix(dir),
+                                file.path.display(),
+                                rejected_date,
+                            )?;
+                        }
+                    }
+
+                    Ok(Some(file))
+                }
+
+                Ok(FoundSourceFile::Unrecognized(path)) => {
+                    writeln!(
+                        env.stderr,
+                        "{} {}{}: unrecognized",
+                        "warn".yellow(),
+                        prefix(dir),
+                        path.display()
+                    )?;
+
+                    unrecognized.push(path);
+
+                    Ok(None)
+                }
+
+                Ok(FoundSourceFile::NonUnicode(path)) => {
+                    writeln!(
+                        env.stderr,
+                        "{} {}{}: name is not valid unicode",
+                        "warn".yellow(),
+                        prefix(dir),
+                        path.display()
+                    )?;
+
+                    unrecognized.push(path);
+
+                    Ok(None)
+                }
+
+                Err(err) => {
+                    writeln!(env.stderr, "{} {}{:#}", "warn".yellow(), prefix(dir), err)?;
+
+                    failed += 1;
+
+                    Ok(None)
+                }
+            })
+            .flatten_ok()
+            .filter_ok(|file| self.filter.matches(file.ty.date()))
+            .filter_ok(|file| self.matches_time(file))
+            .filter_ok(|file| self.types.is_empty() || self.types.contains(&file.ty.kind()))
+            .filter_ok(|file| last_run.is_none_or(|last_run| file.modified_at() > last_run))
+            .collect::<Result<_>>()?;
+
+        let (mut files, too_large) = self.filter_size(env, files)?;
+
+        files.sort_by_key(|file| file.path.clone());
+
+        Self::pair_raw_photos(&mut files);
+
+        if self.live_photos {
+            Self::pair_live_photos(env, &mut files)?;
+        }
+
+        // Sorting by date happens after pairing, so that paired files (which
+        // get the same date) end up next to each other
+        if self.sort == SortBy::Date {
+            files.sort_by_key(|file| (file.ty.date(), file.ty.datetime(), file.path.clone()));
+        }
+
+        if self.reverse {
+            files.reverse();
+        }
+
+        writeln!(env.stdout)?;
+
+        Ok(Sources {
+            files,
+            unrecognized,
+            failed,
+            too_large,
+            scanned_at,
+        })
+    }
+
+    /// Returns range of dates which are considered sane - media dated outside
+    /// of it (e.g. `1970-01-01`) most likely have bogus metadata.
+    fn date_range(&self) -> RangeInclusive<NaiveDate> {
+        let max_date = self.max_date.unwrap_or_else(|| {
+            Local::now()
+                .date_naive()
+                .succ_opt()
+                .unwrap_or(NaiveDate::MAX)
+        });
+
+        self.min_date..=max_date
+    }
+
+    /// Rejects files larger than `--max-size`, returning how many of them
+    /// there were.
+    fn filter_size(
+        &self,
+        env: &mut Env,
+        files: Vec<SourceFile>,
+    ) -> Result<(Vec<SourceFile>, usize)> {
+        let Some(max_size) = self.max_size else {
+            return Ok((files, 0));
+        };
+
+        let mut accepted = Vec::new();
+        let mut too_large = 0;
+
+        for file in files {
+            let size = fs::metadata(&file.path)
+                .with_context(|| format!("couldn't read metadata: {}", file.path.display()))?
+                .len();
+
+            if size > max_size {
+                writeln!(
+                    env.stderr,
+                    "{} {}: too large ({})",
+                    "warn".yellow(),
+                    file.path.display(),
+                    format_size(size),
+                )?;
+
+                too_large += 1;
+            } else {
+                accepted.push(file);
+            }
+        }
+
+        Ok((accepted, too_large))
+    }
+
+    /// Checks file against `--after-time` and `--before-time`; when any of
+    /// those is active, files without a known time (i.e. notes) are rejected.
+    ///
+    /// If `--after-time` is later than `--before-time`, the window wraps
+    /// around midnight (e.g. `--after-time 22:00 --before-time 02:00`).
+    fn matches_time(&self, file: &SourceFile) -> bool {
+        if self.after_time.is_none() && self.before_time.is_none() {
+            return true;
+        }
+
+        let Some(time) = file.ty.datetime().map(|dt| dt.time()) else {
+            return false;
+        };
+
+        match (self.after_time, self.before_time) {
+            (Some(after), Some(before)) if after > before => time >= after || time < before,
+            (after, before) => {
+                after.is_none_or(|after| time >= after) && before.is_none_or(|before| time < before)
+            }
+        }
+    }
+
+    /// Finds RAW photos shot together with a JPEG (e.g. `DSC001.ARW` +
+    /// `DSC001.JPG`) and makes them use JPEG's timestamp, so that both get the
+    /// same name in the diary.
+    fn pair_raw_photos(files: &mut [SourceFile]) {
+        let jpgs: HashMap<_, _> = files
+            .iter()
+            .filter(|file| matches!(file.ext.as_str(), "jpg" | "jpeg"))
+            .filter_map(|file| match &file.ty {
+                SourceFileType::Photo { date, .. } => Some((
+                    (file.path.parent().map(PathBuf::from), file.stem.clone()),
+                    *date,
+                )),
+                _ => None,
+            })
+            .collect();
+
+        for file in files.iter_mut() {
+            if !FileKind::is_raw(&file.ext) {
+                continue;
+            }
+
+            let key = (file.path.parent().map(PathBuf::from), file.stem.clone());
+
+            if let (Some(jpg_date), SourceFileType::Photo { date, .. }) =
+                (jpgs.get(&key), &mut file.ty)
+            {
+                *date = *jpg_date;
+            }
+        }
+    }
+
+    /// Finds Live Photos (i.e. photos and videos sharing the same id, such as
+    /// `IMG_1234.HEIC` + `IMG_1234.MOV`) and makes the videos inherit photos'
+    /// timestamps, so that both land in the diary side by side.
+    fn pair_live_photos(env: &mut Env, files: &mut [SourceFile]) -> Result<()> {
+        let photos: HashMap<_, _> = files
+            .iter()
+            .filter_map(|file| match &file.ty {
+                SourceFileType::Photo { date, id: Some(id) } => Some((
+                    (file.path.parent().map(PathBuf::from), id.clone()),
+                    (*date, file.path.clone()),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        for file in files.iter_mut() {
+            let SourceFileType::Video { date, id: Some(id) } = &mut file.ty else {
+                continue;
+            };
+
+            let key = (file.path.parent().map(PathBuf::from), id.clone());
+
+            if let Some((photo_date, photo_path)) = photos.get(&key) {
+                *date = *photo_date;
+
+                writeln!(
+                    env.stdout,
+                    "  {} `{}` with `{}`",
+                    "paired".green(),
+                    photo_path.display(),
+                    file.path.display(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn plan(&self, env: &mut Env, srcs: &Sources) -> Result<Plan> {
+        let _span = info_span!("plan").entered();
+
+        writeln!(env.stdout, "{}", "planning".green().bold())?;
+
+        let mut plan = Plan::default();
+        let diary = self.open_diary(&env.config)?;
+        let mut hashes = HashMap::new();
+        let mut planned = HashSet::new();
+        let files = &srcs.files;
+        let duplicated_notes = self.find_duplicated_notes(files)?;
+        let note_assets = self.find_note_assets(env, files)?;
+        let events = self.find_events(files);
+
+        for file in files {
+            // (added together with the note linking to it)
+            if note_assets.contains(&file.path) {
+                continue;
+            }
+
+            let steps = match &file.ty {
+                SourceFileType::Note { date, .. } => match duplicated_notes.get(date) {
+                    Some(notes) if notes[0].path == file.path => {
+                        writeln!(
+                            env.stdout,
+                            "  {} notes for {}: {}",
+                            "merging".green(),
+                            date,
+                            notes
+                                .iter()
+                                .map(|note| format!("`{}`", note.path.display()))
+                                .join(", "),
+                        )?;
+
+                        self.plan_merge(&diary, &mut planned, notes, *date)?
+                    }
+
+                    // (merged together with the first note of that day)
+                    Some(_) => continue,
+
+                    None => self.plan_note(&diary, &mut planned, file, *date)?,
+                },
+
+                SourceFileType::Photo { date, id } | SourceFileType::Audio { date, id } => self
+                    .plan_media(
+                        &diary,
+                        &mut planned,
+                        file,
+                        *date,
+                        id.as_deref(),
+                        events.get(&file.path).copied(),
+                    )?,
+
+                SourceFileType::Video { date, id } => self.plan_video(
+                    &diary,
+                    &mut planned,
+                    files,
+                    file,
+                    *date,
+                    id.as_deref(),
+                    events.get(&file.path).copied(),
+                )?,
+            };
+
+            let steps = if self.dedup {
+                self.plan_dedup(&diary, &mut hashes, file, steps)?
+            } else {
+                steps
+            };
+
+            let steps = self.plan_note_assets(&diary, &mut planned, &note_assets, file, steps)?;
+            let steps = self.plan_overwrite(&diary, steps)?;
+            let steps = self.plan_tags(&diary, steps);
+
+            debug!(path = %file.path.display(), steps = steps.len(), "planned file");
+
+            plan.steps.extend(steps);
+        }
+
+        if self.with_note {
+            let notes = self.plan_notes(&diary, &plan.steps)?;
+
+            plan.steps.extend(notes);
+        }
+
+        if let Some(quarantine) = &self.quarantine {
+            plan.steps
+                .extend(self.plan_quarantine(quarantine, &srcs.unrecognized));
+        }
+
+        writeln!(env.stdout)?;
+
+        Ok(plan)
+    }
+
+    fn plan_note(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        file: &SourceFile,
+        file_dt: NaiveDate,
+    ) -> Result<Vec<Step>> {
+        let stem = self.note_stem(file_dt);
+        let dst = diary.file_id(file_dt, format!("{}.{}", stem, file.ext));
+
+        if self.append && diary.has(&dst)? {
+            return self.plan_append(diary, file, dst);
+        }
+
+        let dst = self.plan_dst(
+            diary,
+            planned,
+            &file.path,
+            dst,
+            |dst| Ok(diary.hash(dst)? == hash_file(&file.path)?),
+            |n| diary.file_id(file_dt, format!("{} ({}).{}", stem, n, file.ext)),
+        )?;
+
+        match dst {
+            PlannedDst::Free(dst) => Ok(self.plan_add(&file.path, dst)),
+            PlannedDst::Taken(step) => Ok(vec![step]),
+        }
+    }
+
+    /// Returns notes which share their date with another note, grouped by that
+    /// date (`.org` ones go first); fails if there are any, unless
+    /// `--merge-notes` is active.
+    fn find_duplicated_notes<'a>(
+        &self,
+        files: &'a [SourceFile],
+    ) -> Result<BTreeMap<NaiveDate, Vec<&'a SourceFile>>> {
+        let mut notes: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+        for file in files {
+            if let SourceFileType::Note { date, .. } = &file.ty {
+                notes.entry(*date).or_default().push(file);
+            }
+        }
+
+        notes.retain(|_, notes| notes.len() > 1);
+
+        for notes in notes.values_mut() {
+            notes.sort_by_key(|note| (note.ext != "org", note.path.clone()));
+        }
+
+        if !self.merge_notes {
+            if let Some((date, notes)) = notes.first_key_value() {
+                bail!(
+                    "found {} notes for {} ({}) - pass `--merge-notes` to merge them into one",
+                    notes.len(),
+                    date,
+                    notes
+                        .iter()
+                        .map(|note| format!("`{}`", note.path.display()))
+                        .join(", "),
+                );
+            }
+        }
+
+        Ok(notes)
+    }
+
+    /// Clusters media of each day into events - runs of media taken within
+    /// `--event-gap` minutes of each other - and returns the event each file
+    /// belongs to, numbered from 1 (see `--events`).
+    ///
+    /// Days that turn out to have a single event are left as they are.
+    fn find_events(&self, files: &[SourceFile]) -> HashMap<PathBuf, usize> {
+        let mut events = HashMap::new();
+
+        if !self.events {
+            return events;
+        }
+
+        let mut days: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+        for file in files {
+            let date = match &file.ty {
+                SourceFileType::Photo { date, .. }
+                | SourceFileType::Video { date, .. }
+                | SourceFileType::Audio { date, .. } => *date,
+
+                SourceFileType::Note { .. } => continue,
+            };
+
+            days.entry(date.date())
+                .or_default()
+                .push((date, &file.path));
+        }
+
+        let gap = TimeDelta::minutes(self.event_gap.into());
+
+        for mut media in days.into_values() {
+            media.sort();
+
+            let mut event = 1;
+            let mut day_events = Vec::with_capacity(media.len());
+
+            for (idx, (date, path)) in media.iter().enumerate() {
+                if idx > 0 && *date - media[idx - 1].0 > gap {
+                    event += 1;
+                }
+
+                day_events.push((path.to_path_buf(), event));
+            }
+
+            if event > 1 {
+                events.extend(day_events);
+            }
+        }
+
+        events
+    }
+
+    /// Returns files linked from notes (see `--copy-note-assets`), warning
+    /// about links that point at missing files.
+    fn find_note_assets(&self, env: &mut Env, files: &[SourceFile]) -> Result<NoteAssets> {
+        let mut assets = NoteAssets::default();
+
+        if !self.copy_note_assets {
+            return Ok(assets);
+        }
+
+        for file in files {
+            if !matches!(file.ty, SourceFileType::Note { .. }) {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&file.path)
+                .with_context(|| format!("couldn't read: {}", file.path.display()))?;
+
+            let dir = file.path.parent().unwrap_or(Path::new(""));
+
+            for link in find_note_links(&contents, &file.ext) {
+                let path = dir.join(&link);
+
+                // (links between notes are left alone)
+                let is_note = path
+                    .extension()
+                    .and_then(|ext| FileKind::from_ext(&ext.to_string_lossy().to_lowercase()))
+                    == Some(FileKind::Note);
+
+                if is_note {
+                    continue;
+                }
+
+                if !path.is_file() {
+                    writeln!(
+                        env.stderr,
+                        "{} {}: linked file `{}` not found",
+                        "warn".yellow(),
+                        file.path.display(),
+                        link,
+                    )?;
+
+                    continue;
+                }
+
+                assets.files.insert(
+                    fs::canonicalize(&path)
+                        .with_context(|| format!("couldn't resolve: {}", path.display()))?,
+                );
+
+                assets
+                    .links
+                    .entry(file.path.clone())
+                    .or_default()
+                    .push((link, path));
+            }
+        }
+
+        Ok(assets)
+    }
+
+    /// Extends the plan with steps adding files linked from the note into the
+    /// note's day, rewriting the links so that they keep working (see
+    /// `--copy-note-assets`).
+    fn plan_note_assets(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        assets: &NoteAssets,
+        file: &SourceFile,
+        mut steps: Vec<Step>,
+    ) -> Result<Vec<Step>> {
+        let Some(links) = assets.links.get(&file.path) else {
+            return Ok(steps);
+        };
+
+        let note = steps.iter().find_map(|step| match step {
+            Step::Copy { dst, .. } | Step::Move { dst, .. } | Step::Append { dst, .. } => {
+                Some(dst.clone())
+            }
+            _ => None,
+        });
+
+        let date = file.ty.date();
+        let mut relinks = Vec::new();
+
+        for (link, path) in links {
+            let name = path
+                .file_name()
+                .context("linked file has no name")?
+                .to_string_lossy()
+                .into_owned();
+
+            let (stem, ext) = match name.rsplit_once('.') {
+                Some((stem, ext)) => (stem.to_owned(), format!(".{}", ext)),
+                None => (name.clone(), String::new()),
+            };
+
+            // Remembers the file that's turned out to be the same as the linked
+            // one, so that the link can point at it
+            let same = Cell::new(None);
+
+            let dst = self.plan_dst(
+                diary,
+                planned,
+                path,
+                diary.file_id(date, &name),
+                |dst| {
+                    let is_same = diary.hash(dst)? == hash_file(path)?;
+
+                    if is_same {
+                        same.set(Some(dst.clone()));
+                    }
+
+                    Ok(is_same)
+                },
+                |n| diary.file_id(date, format!("{} ({}){}", stem, n, ext)),
+            )?;
+
+            let dst = match dst {
+                PlannedDst::Free(dst) => {
+                    steps.extend(self.plan_add(path, dst.clone()));
+                    Some(dst)
+                }
+
+                PlannedDst::Taken(step) => {
+                    steps.push(step);
+                    same.take()
+                }
+            };
+
+            if let (Some(note), Some(dst)) = (&note, dst) {
+                let note_dir = diary.rel_file(note);
+                let note_dir = note_dir.parent().unwrap_or(Path::new(""));
+
+                let target = relative_path(note_dir, &diary.rel_file(&dst))
+                    .to_string_lossy()
+                    .into_owned();
+
+                if &target != link {
+                    relinks.push((link.clone(), target));
+                }
+            }
+        }
+
+        if let Some(note) = note {
+            if !relinks.is_empty() {
+                steps.push(Step::Relink {
+                    src: file.path.clone(),
+                    dst: note,
+                    links: relinks,
+                });
+            }
+        }
+
+        Ok(steps)
+    }
+
+    /// Merges notes of the same day into a single org note, each note (other
+    /// than the first one) preceded by a `* Merged from <name>` heading.
+    fn plan_merge(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        notes: &[&SourceFile],
+        date: NaiveDate,
+    ) -> Result<Vec<Step>> {
+        let stem = self.note_stem(date);
+        let dst = diary.file_id(date, format!("{}.org", stem));
+
+        if self.append && diary.has(&dst)? {
+            return notes
+                .iter()
+                .map(|note| self.plan_append(diary, note, dst.clone()))
+                .flatten_ok()
+                .collect();
+        }
+
+        let mut contents = String::new();
+
+        for (idx, note) in notes.iter().enumerate() {
+            let src = fs::read_to_string(&note.path)
+                .with_context(|| format!("couldn't read: {}", note.path.display()))?;
+
+            if idx > 0 {
+                contents += &format!("\n* Merged from {}.{}\n\n", note.stem, note.ext);
+            }
+
+            contents += src.trim_end();
+            contents += "\n";
+        }
+
+        let dst = self.plan_dst(
+            diary,
+            planned,
+            &notes[0].path,
+            dst,
+            |dst| Ok(diary.read(dst)? == contents),
+            |n| diary.file_id(date, format!("{} ({}).org", stem, n)),
+        )?;
+
+        let dst = match dst {
+            PlannedDst::Free(dst) => dst,
+
+            // Whatever happens to the first note, happens to the rest of them
+            PlannedDst::Taken(step) => {
+                return Ok(notes
+                    .iter()
+                    .map(|note| match &step {
+                        Step::Skip { reason, .. } => Step::Skip {
+                            src: note.path.clone(),
+                            reason: reason.clone(),
+                        },
+                        Step::Remove { reason, .. } => Step::Remove {
+                            src: note.path.clone(),
+                            reason: reason.clone(),
+                        },
+                        _ => unreachable!(),
+                    })
+                    .collect());
+            }
+        };
+
+        let merge = Step::Merge {
+            srcs: notes.iter().map(|note| note.path.clone()).collect(),
+            dst,
+            contents,
+        };
+
+        let remove = notes
+            .iter()
+            .filter(|_| self.remove || self.r#move)
+            .map(|note| Step::Remove {
+                src: note.path.clone(),
+                reason: "just merged into the diary".into(),
+            });
+
+        Ok(iter::once(merge).chain(remove).collect())
+    }
+
+    fn plan_append(
+        &self,
+        diary: &DiaryRepository,
+        file: &SourceFile,
+        dst: DiaryFileId,
+    ) -> Result<Vec<Step>> {
+        let src = fs::read_to_string(&file.path)
+            .with_context(|| format!("couldn't read: {}", file.path.display()))?;
+
+        let src = src.trim();
+
+        if src.is_empty() || diary.read(&dst)?.trim_end().ends_with(src) {
+            return Ok(vec![Step::skip_or_remove(
+                file.path.clone(),
+                "already in the diary",
+                self.remove || self.r#move,
+            )]);
+        }
+
+        let append = Step::Append {
+            src: file.path.clone(),
+            dst,
+            contents: format!("\n* Appended from {}.{}\n\n{}\n", file.stem, file.ext, src),
+        };
+
+        // (appending copies the contents, so with `--move` the source has to
+        // be removed separately, as with merged notes)
+        let remove = (self.remove || self.r#move).then(|| Step::Remove {
+            src: file.path.clone(),
+            reason: "just added into the diary".into(),
+        });
+
+        Ok(iter::once(append).chain(remove).collect())
+    }
+
+    fn plan_media(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        file: &SourceFile,
+        file_dt: NaiveDateTime,
+        file_id: Option<&str>,
+        event: Option<usize>,
+    ) -> Result<Vec<Step>> {
+        let convert = self.convert_heic && matches!(file.ext.as_str(), "heic" | "heif");
+        let name = self.get_media_name(file, file_dt, file_id, event);
+        let ext = if convert {
+            "jpg"
+        } else {
+            self.get_media_ext(file)
+        };
+        let dst = diary.file_id(file_dt.date(), format!("{}.{}", name, ext));
+
+        // Converted file is never byte-for-byte the same as its source, so
+        // when converting, we can't tell whatever's under given name apart
+        // from an unrelated file - that's left to `--on-conflict`
+        let dst = self.plan_dst(
+            diary,
+            planned,
+            &file.path,
+            dst,
+            |dst| Ok(!convert && diary.hash(dst)? == hash_file(&file.path)?),
+            |n| diary.file_id(file_dt.date(), format!("{} ({}).{}", name, n, ext)),
+        )?;
+
+        let dst = match dst {
+            PlannedDst::Free(dst) => dst,
+            PlannedDst::Taken(step) => return Ok(vec![step]),
+        };
+
+        if convert {
+            let remove = (self.remove || self.r#move).then(|| Step::Remove {
+                src: file.path.clone(),
+                reason: "just added into the diary".into(),
+            });
+
+            let steps = iter::once(Step::Convert {
+                src: file.path.clone(),
+                dst,
+            })
+            .chain(remove)
+            .collect();
+
+            return self.plan_thumbnail(diary, planned, steps);
+        }
+
+        self.plan_thumbnail(diary, planned, self.plan_add(&file.path, dst))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn plan_video(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        files: &[SourceFile],
+        file: &SourceFile,
+        file_dt: NaiveDateTime,
+        file_id: Option<&str>,
+        event: Option<usize>,
+    ) -> Result<Vec<Step>> {
+        let name = self.get_media_name(file, file_dt, file_id, event);
+        let mk = |ext: &str| diary.file_id(file_dt.date(), format!("{}.{}", name, ext));
+
+        // (animated images keep their format)
+        let ext = if FileKind::is_animatable(&file.ext) {
+            self.get_media_ext(file)
+        } else {
+            "mp4"
+        };
+
+        let dst_jpg = mk("jpg");
+        let dst_png = mk("png");
+        let dst_heic = mk("heic");
+
+        let dst = self.plan_dst(
+            diary,
+            planned,
+            &file.path,
+            mk(ext),
+            |dst| Ok(diary.hash(dst)? == hash_file(&file.path)?),
+            |n| diary.file_id(file_dt.date(), format!("{} ({}).{}", name, n, ext)),
+        )?;
+
+        let dst = match dst {
+            PlannedDst::Free(dst) => dst,
+            PlannedDst::Taken(step) => return Ok(vec![step]),
+        };
+
+        let has_photo = diary.has(&dst_jpg)? || diary.has(&dst_png)? || diary.has(&dst_heic)?;
+
+        let will_have_photo = file_id.is_some()
+            && files.iter().any(|src| {
+                if let SourceFileType::Photo { id: id2, .. } = &src.ty {
+                    file_id == id2.as_deref()
+                } else {
+                    false
+                }
+            });
+
+        if (has_photo || will_have_photo) && !self.live_photos {
+            return Ok(vec![Step::skip_or_remove(
+                file.path.clone(),
+                "already in the diary as a photo",
+                self.remove || self.r#move,
+            )]);
+        }
+
+        self.plan_thumbnail(diary, planned, self.plan_add(&file.path, dst))
+    }
+
+    /// Checks whether `dst` is free and, if it's not, consults
+    /// `--on-conflict` on what to do.
+    ///
+    /// A file that's already in the diary (as determined by `is_same`) is not
+    /// a conflict - it's simply skipped.
+    fn plan_dst(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        src: &Path,
+        mut dst: DiaryFileId,
+        is_same: impl Fn(&DiaryFileId) -> Result<bool>,
+        rename: impl Fn(usize) -> DiaryFileId,
+    ) -> Result<PlannedDst> {
+        for n in 2.. {
+            let is_planned = planned.contains(&dst.to_string());
+            let is_taken = !is_planned && diary.has(&dst)?;
+
+            // (files on remotes can't be compared, so they always conflict)
+            if is_taken && !diary.is_remote() && is_same(&dst)? {
+                return Ok(PlannedDst::Taken(Step::skip_or_remove(
+                    src.to_owned(),
+                    "already in the diary",
+                    self.remove || self.r#move,
+                )));
+            }
+
+            if !is_planned && !is_taken {
+                break;
+            }
+
+            match self.on_conflict {
+                OnConflict::Skip => {
+                    return Ok(PlannedDst::Taken(Step::Skip {
+                        src: src.to_owned(),
+                        reason: format!("would overwrite `{}`", dst),
+                    }));
+                }
+
+                OnConflict::Overwrite => {
+                    break;
+                }
+
+                OnConflict::Rename => {
+                    dst = rename(n);
+                }
+
+                OnConflict::Fail => {
+                    bail!(
+                        "cannot add `{}` into diary, because it would overwrite `{}`",
+                        src.display(),
+                        dst,
+                    );
+                }
+            }
+        }
+
+        planned.insert(dst.to_string());
+
+        Ok(PlannedDst::Free(dst))
+    }
+
+    fn plan_add(&self, src: &Path, dst: DiaryFileId) -> Vec<Step> {
+        if self.r#move {
+            vec![Step::Move {
+                src: src.to_owned(),
+                dst,
+            }]
+        } else if self.symlink || self.hardlink {
+            vec![Step::Link {
+                src: src.to_owned(),
+                dst,
+            }]
+        } else {
+            Step::copy_and_remove(src.to_owned(), dst, self.remove).collect()
+        }
+    }
+
+    /// Extends the plan with a step generating thumbnail for the media file
+    /// that's about to be added (if `--thumbnails` is active).
+    fn plan_thumbnail(
+        &self,
+        diary: &DiaryRepository,
+        planned: &mut HashSet<String>,
+        mut steps: Vec<Step>,
+    ) -> Result<Vec<Step>> {
+        if !self.thumbnails {
+            return Ok(steps);
+        }
+
+        let Some((idx, src, media)) = steps.iter().enumerate().find_map(|(idx, step)| match step {
+            Step::Copy { src, dst }
+            | Step::Move { src, dst }
+            | Step::Link { src, dst }
+            | Step::Convert { src, dst } => Some((idx, src, dst)),
+            _ => None,
+        }) else {
+            return Ok(steps);
+        };
+
+        if !matches!(media.kind(), Some(FileKind::Photo | FileKind::Video)) {
+            return Ok(steps);
+        }
+
+        let dst = diary.thumbnail_id(media);
+
+        if !planned.contains(&dst.to_string()) && !diary.has(&dst)? {
+            planned.insert(dst.to_string());
+
+            let src = src.clone();
+            let media = diary.file_id(media.date, &media.name);
+
+            steps.insert(idx + 1, Step::Thumbnail { src, media, dst });
+        }
+
+        Ok(steps)
+    }
+
+    fn plan_dedup(
+        &self,
+        diary: &DiaryRepository,
+        hashes: &mut HashMap<NaiveDate, HashSet<blake3::Hash>>,
+        file: &SourceFile,
+        steps: Vec<Step>,
+    ) -> Result<Vec<Step>> {
+        let Some(dst) = steps.iter().find_map(|step| match step {
+            Step::Copy { dst, .. }
+            | Step::Move { dst, .. }
+            | Step::Link { dst, .. }
+            | Step::Convert { dst, .. } => Some(dst),
+            _ => None,
+        }) else {
+            return Ok(steps);
+        };
+
+        let hashes = match hashes.entry(dst.date) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(diary.hashes(dst.date)?),
+        };
+
+        if hashes.insert(hash_file(&file.path)?) {
+            Ok(steps)
+        } else {
+            Ok(vec![Step::skip_or_remove(
+                file.path.clone(),
+                "duplicate content",
+                self.remove || self.r#move,
+            )])
+        }
+    }
+
+    /// Marks steps which are about to replace files already present in the
+    /// diary (possible only with `--on-conflict overwrite`), so that they stand
+    /// out in the plan.
+    fn plan_overwrite(&self, diary: &DiaryRepository, steps: Vec<Step>) -> Result<Vec<Step>> {
+        if self.on_conflict != OnConflict::Overwrite {
+            return Ok(steps);
+        }
+
+        steps
+            .into_iter()
+            .map(|step| {
+                let (Step::Copy { dst, .. }
+                | Step::Move { dst, .. }
+                | Step::Link { dst, .. }
+                | Step::Convert { dst, .. }) = &step
+                else {
+                    return Ok(step);
+                };
+
+                if !diary.has(dst)? {
+                    return Ok(step);
+                }
+
+                let (src, dst, mode) = match step {
+                    Step::Copy { src, dst } => (src, dst, AddMode::Copy),
+                    Step::Move { src, dst } => (src, dst, AddMode::Move),
+                    Step::Link { src, dst } => (src, dst, self.link_mode()),
+                    Step::Convert { src, dst } => (src, dst, AddMode::ConvertHeic),
+                    _ => unreachable!(),
+                };
+
+                Ok(Step::Overwrite { src, dst, mode })
+            })
+            .collect()
+    }
+
+    /// Tags files that are about to be added (see `--tag`).
+    ///
+    /// Org notes get tagged through their `#+FILETAGS` header; other files
+    /// (and linked notes, which mustn't be modified) get a `.tags` sidecar.
+    fn plan_tags(&self, diary: &DiaryRepository, mut steps: Vec<Step>) -> Vec<Step> {
+        if self.tags.is_empty() {
+            return steps;
+        }
+
+        let Some((idx, src, file, linked)) =
+            steps.iter().enumerate().find_map(|(idx, step)| match step {
+                Step::Copy { src, dst }
+                | Step::Move { src, dst }
+                | Step::Convert { src, dst }
+                | Step::Append { src, dst, .. } => Some((idx, src, dst, false)),
+
+                Step::Merge { srcs, dst, .. } => Some((idx, &srcs[0], dst, false)),
+
+                Step::Link { src, dst } => Some((idx, src, dst, true)),
+
+                Step::Overwrite { src, dst, mode } => Some((
+                    idx,
+                    src,
+                    dst,
+                    matches!(mode, AddMode::Symlink | AddMode::Hardlink),
+                )),
+
+                _ => None,
+            })
+        else {
+            return steps;
+        };
+
+        let is_org = Path::new(&file.name)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("org"));
+
+        let dst = if is_org && !linked {
+            diary.file_id(file.date, &file.name)
+        } else {
+            diary.tags_id(file)
+        };
+
+        let tag = Step::Tag {
+            src: src.clone(),
+            dst,
+            tags: self.tags.clone(),
+        };
+
+        steps.insert(idx + 1, tag);
+        steps
+    }
+
+    /// Creates notes for days which are about to get media, but which don't
+    /// have a note yet.
+    fn plan_notes(&self, diary: &DiaryRepository, steps: &[Step]) -> Result<Vec<Step>> {
+        let mut media_dates = BTreeSet::new();
+        let mut note_dates = HashSet::new();
+
+        for step in steps {
+            let (Step::Copy { dst, .. }
+            | Step::Move { dst, .. }
+            | Step::Link { dst, .. }
+            | Step::Convert { dst, .. }
+            | Step::Overwrite { dst, .. }
+            | Step::Merge { dst, .. }) = step
+            else {
+                continue;
+            };
+
+            if dst.kind() == Some(FileKind::Note) {
+                note_dates.insert(dst.date);
+            } else {
+                media_dates.insert(dst.date);
+            }
+        }
+
+        let mut notes = Vec::new();
+
+        for date in media_dates {
+            let dst = diary.file_id(date, format!("{}.org", self.note_stem(date)));
+
+            if note_dates.contains(&date) || diary.has(&dst)? {
+                continue;
+            }
+
+            #[allow(clippy::literal_string_with_formatting_args)]
+            let header = self.note_header.replace("{date}", &date.to_string());
+
+            notes.push(Step::Create {
+                dst,
+                contents: format!("{}\n", header),
+            });
+        }
+
+        Ok(notes)
+    }
+
+    /// Puts unrecognized files aside, so that they don't get lost.
+    ///
+    /// With `--remove` or `--move`, sources get removed through a separate
+    /// step, so that the removal is confirmed (and journaled, or trashed) as
+    /// any other.
+    fn plan_quarantine(&self, quarantine: &Path, paths: &[PathBuf]) -> Vec<Step> {
+        paths
+            .iter()
+            .flat_map(|path| {
+                let quarantine = Step::Quarantine {
+                    src: path.clone(),
+                    dst: quarantine.join(self.source_rel_path(path)),
+                };
+
+                let remove = (self.remove || self.r#move).then(|| Step::Remove {
+                    src: path.clone(),
+                    reason: "just quarantined".into(),
+                });
+
+                iter::once(quarantine).chain(remove)
+            })
+            .collect()
+    }
+
+    /// Returns path of given source file relative to the source it comes from,
+    /// so that files moved out of sources (see `--quarantine` and
+    /// `--trash-dir`) keep their structure.
+    fn source_rel_path<'a>(&self, path: &'a Path) -> &'a Path {
+        self.source
+            .iter()
+            .find_map(|source| path.strip_prefix(source).ok())
+            .unwrap_or(path)
+    }
+
+    fn get_media_name(
+        &self,
+        file: &SourceFile,
+        dt: NaiveDateTime,
+        id: Option<&str>,
+        event: Option<usize>,
+    ) -> String {
+        let label = get_media_label(
+            &file.stem,
+            &self.screenshot_prefixes,
+            &self.screencast_prefixes,
+        );
+
+        let name = get_media_name(&file.stem, dt, id, label, self.name_template.as_ref());
+
+        // Flattened import lacks per-day directories, so there the date has to
+        // be a part of the name
+        let name = if self.prefix_date || self.flatten.is_some() {
+            format!("{} {}", dt.format("%Y-%m-%d"), name)
+        } else {
+            name
+        };
+
+        let burst = (!self.no_group_bursts && file.ty.kind() == FileKind::Photo)
+            .then(|| get_burst_name(&file.stem))
+            .flatten();
+
+        let name = match burst {
+            Some(burst) => format!("{}/{}/{}", BURSTS_DIR, burst, name),
+            None => name,
+        };
+
+        let name = match event {
+            Some(event) => format!("{}{}/{}", EVENT_DIR_PREFIX, event, name),
+            None => name,
+        };
+
+        if self.keep_structure && !file.rel_dir.as_os_str().is_empty() {
+            let dir = file
+                .rel_dir
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .join("/");
+
+            format!("{}/{}", dir, name)
+        } else {
+            name
+        }
+    }
+
+    fn get_media_ext<'a>(&'a self, file: &'a SourceFile) -> &'a str {
+        if self.normalize_ext {
+            normalize_ext(&file.ext, &self.ext_aliases)
+        } else {
+            &file.ext
+        }
+    }
+
+    fn exec(&self, env: &mut Env, plan: Plan, dry_run: bool) -> Result<Stats> {
+        let _span = info_span!("exec", dry_run).entered();
+
+        writeln!(env.stdout, "{}", "executing".green().bold())?;
+
+        let mut diary = self
+            .open_diary(&env.config)?
+            .with_preserve_time(!self.no_preserve_time)
+            .with_on_conflict(self.on_conflict);
+
+        if let Some(size) = self.copy_buffer_size {
+            diary = diary.with_copy_buffer_size(size.try_into()?);
+        }
+
+        let mut stats = Stats::default();
+        let step_count = plan.steps.len();
+
+        let journal_id = if self.journal && !dry_run {
+            Some((diary.journal()?.len() + 1).to_string())
+        } else {
+            None
+        };
+
+        // Progress bar replaces the per-step lines, unless the user asked to
+        // see them (which is the whole point of a dry run)
+        let mut ask =
+            self.interactive == Some(Interactive::Each) && !dry_run && io::stdin().is_terminal();
+
+        #[allow(clippy::literal_string_with_formatting_args)]
+        let progress = (env.is_terminal && !self.verbose && !dry_run && !ask).then(|| {
+            ProgressBar::new(step_count as u64).with_style(
+                ProgressStyle::with_template("  {bar:40} {pos}/{len} ({per_sec}, eta {eta}) {msg}")
+                    .unwrap(),
+            )
+        });
+
+        let result: Result<()> = try {
+            for (step_idx, step) in plan.steps.into_iter().enumerate() {
+                debug!(?step, "executing step");
+
+                if let Some(progress) = &progress {
+                    env.muted(|env| {
+                        let ctxt = ExecCtxt {
+                            env,
+                            stats: &mut stats,
+                            diary: &mut diary,
+                            step_idx,
+                            step_count,
+                            dry_run,
+                            journal_id: journal_id.as_deref(),
+                            progress: Some(progress),
+                        };
+
+                        self.exec_step(ctxt, step)
+                    })?;
+
+                    progress.inc(1);
+                    progress.set_message("");
+                } else {
+                    let step = if ask {
+                        match Self::ask(env, &step)? {
+                            Answer::Yes => step,
+
+                            Answer::No => match step {
+                                Step::Copy { src, .. }
+                                | Step::Move { src, .. }
+                                | Step::Link { src, .. }
+                                | Step::Convert { src, .. }
+                                | Step::Overwrite { src, .. } => {
+                                    stats.not_added.insert(src.clone());
+
+                                    Step::Skip {
+                                        src,
+                                        reason: "declined".into(),
+                                    }
+                                }
+                                step => step,
+                            },
+
+                            Answer::All => {
+                                ask = false;
+                                step
+                            }
+                        }
+                    } else {
+                        step
+                    };
+
+                    let ctxt = ExecCtxt {
+                        env,
+                        stats: &mut stats,
+                        diary: &mut diary,
+                        step_idx,
+                        step_count,
+                        dry_run,
+                        journal_id: journal_id.as_deref(),
+                        progress: None,
+                    };
+
+                    self.exec_step(ctxt, step)?;
+                }
+            }
+        };
+
+        // Cleared before a possible error gets reported, so that it doesn't
+        // remain half-drawn above it
+        if let Some(progress) = progress {
+            progress.finish_and_clear();
+        }
+
+        // Record what's been done even if some step has failed, so that the
+        // partial import can be undone as well
+        if !stats.journal.is_empty() {
+            diary.push_journal(&JournalEntry {
+                actions: stats.journal.clone(),
+            })?;
+        }
+
+        result?;
+
+        Ok(stats)
+    }
+
+    /// Asks the user whether given step should be executed (see
+    /// `--interactive=each`); steps other than adding files are not asked
+    /// about.
+    fn ask(env: &mut Env, step: &Step) -> Result<Answer> {
+        let (verb, question) = match step {
+            Step::Copy { src, dst } => ("copy", format!("`{}` to `{}`", src.display(), dst)),
+            Step::Move { src, dst } => ("move", format!("`{}` to `{}`", src.display(), dst)),
+            Step::Link { src, dst } => ("link", format!("`{}` to `{}`", src.display(), dst)),
+            Step::Convert { src, dst } => ("convert", format!("`{}` to `{}`", src.display(), dst)),
+
+            Step::Overwrite { src, dst, .. } => {
+                ("overwrite", format!("`{}` with `{}`", dst, src.display()))
+            }
+
+            _ => return Ok(Answer::Yes),
+        };
+
+        write!(
+            env.stdout,
+            "  {} {}? [y/n/a] ",
+            verb.yellow().bold(),
+            question
+        )?;
+
+        env.stdout.flush()?;
+
+        let mut answer = String::new();
+
+        io::stdin()
+            .read_line(&mut answer)
+            .context("couldn't read answer")?;
+
+        Ok(match answer.trim() {
+            "y" | "Y" | "yes" => Answer::Yes,
+            "a" | "A" | "all" => Answer::All,
+            _ => Answer::No,
+        })
+    }
+
+    fn exec_step(&self, ctxt: ExecCtxt, step: Step) -> Result<()> {
+        // Planning has already checked that destinations are free, but a file
+        // could've appeared in the meantime - in that case consult
+        // `--on-conflict` again
+        if let Step::Copy { src, dst }
+        | Step::Move { src, dst }
+        | Step::Link { src, dst }
+        | Step::Convert { src, dst } = &step
+        {
+            if !ctxt.dry_run && ctxt.diary.has(dst)? {
+                match self.on_conflict {
+                    OnConflict::Skip | OnConflict::Rename => {
+                        let reason = format!("would overwrite `{}`", dst);
+
+                        ctxt.stats.not_added.insert(src.clone());
+
+                        return self.exec_skip(ctxt, src.clone(), reason);
+                    }
+
+                    OnConflict::Overwrite => (),
+
+                    OnConflict::Fail => {
+                        bail!(
+                            "cannot add `{}` into diary, because it would overwrite `{}`",
+                            src.display(),
+                            dst,
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Step::Remove { src, .. } = &step {
+            if ctxt.stats.not_added.contains(src) {
+                return self.exec_skip(ctxt, src.clone(), "not added into the diary".into());
+            }
+        }
+
+        if let Step::Tag { src, .. } | Step::Relink { src, .. } = &step {
+            if ctxt.stats.not_added.contains(src) {
+                return self.exec_skip(ctxt, src.clone(), "not added into the diary".into());
+            }
+        }
+
+        if let Step::Thumbnail { src, media, .. } = &step {
+            if ctxt.stats.not_added.contains(src) || (!ctxt.dry_run && !ctxt.diary.has(media)?) {
+                return self.exec_skip(ctxt, src.clone(), "not added into the diary".into());
+            }
+        }
+
+        match step {
+            Step::Copy { src, dst } => {
+                self.exec_copy(ctxt, src, dst)?;
+            }
+            Step::Move { src, dst } => {
+                self.exec_move(ctxt, src, dst)?;
+            }
+            Step::Link { src, dst } => {
+                self.exec_link(ctxt, src, dst)?;
+            }
+            Step::Convert { src, dst } => {
+                self.exec_convert(ctxt, src, dst)?;
+            }
+            Step::Overwrite { src, dst, mode } => {
+                self.exec_overwrite(ctxt, src, dst, mode)?;
+            }
+            Step::Thumbnail { media, dst, .. } => {
+                self.exec_thumbnail(ctxt, media, dst)?;
+            }
+            Step::Tag { dst, tags, .. } => {
+                self.exec_tag(ctxt, dst, tags)?;
+            }
+            Step::Relink { dst, links, .. } => {
+                self.exec_relink(ctxt, dst, links)?;
+            }
+            Step::Append { src, dst, contents } => {
+                self.exec_append(ctxt, src, dst, contents)?;
+            }
+            Step::Merge {
+                srcs,
+                dst,
+                contents,
+            } => {
+                self.exec_merge(ctxt, srcs, dst, contents)?;
+            }
+            Step::Create { dst, contents } => {
+                self.exec_create(ctxt, dst, contents)?;
+            }
+            Step::Quarantine { src, dst } => {
+                self.exec_quarantine(ctxt, src, dst)?;
+            }
+            Step::Skip { src, reason } => {
+                self.exec_skip(ctxt, src, reason)?;
+            }
+            Step::Remove { src, reason } => {
+                self.exec_remove(ctxt, src, reason)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_copy(&self, mut ctxt: ExecCtxt, src: PathBuf, dst: DiaryFileId) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` to `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "copying".green(),
+            src.display(),
+            dst,
+        )?;
+
+        let size = Self::file_size(&src)?;
+
+        if !ctxt.dry_run {
+            let progress = ctxt.progress;
+
+            ctxt.diary
+                .add_with_progress(&src, &dst, AddMode::Copy, &mut |copied| {
+                    Self::report_progress(progress, copied, size)
+                })?;
+
+            self.postprocess(ctxt.diary, &dst)?;
+            ctxt.journal_added(&dst);
+        }
+
+        ctxt.stats.copied += 1;
+        ctxt.stats.copied_bytes += size;
+        ctxt.stats.added.push(dst);
+
+        Ok(())
+    }
+
+    fn exec_move(&self, ctxt: ExecCtxt, src: PathBuf, dst: DiaryFileId) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` to `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "moving".green(),
+            src.display(),
+            dst,
+        )?;
+
+        let size = Self::file_size(&src)?;
+
+        if !ctxt.dry_run {
+            let progress = ctxt.progress;
+
+            ctxt.diary
+                .add_with_progress(&src, &dst, AddMode::Move, &mut |copied| {
+                    Self::report_progress(progress, copied, size)
+                })?;
+
+            self.postprocess(ctxt.diary, &dst)?;
+
+            if ctxt.journal_id.is_some() {
+                ctxt.stats.journal.push(JournalAction::Moved {
+                    src,
+                    dst: ctxt.diary.rel_file(&dst),
+                });
+            }
+        }
+
+        ctxt.stats.moved += 1;
+        ctxt.stats.moved_bytes += size;
+        ctxt.stats.added.push(dst);
+
+        Ok(())
+    }
+
+    /// Shows how much of the file that's being copied is done, so that the
+    /// progress bar doesn't look stuck on large videos.
+    fn report_progress(progress: Option<&ProgressBar>, copied: u64, size: u64) {
+        if let Some(progress) = progress {
+            progress.set_message(format!("{} / {}", format_size(copied), format_size(size)));
+        }
+    }
+
+    /// Applies `--auto-orient` and `--strip-exif` to a file that's just been
+    /// put into the diary - in this order, since orienting needs the metadata.
+    fn postprocess(&self, diary: &DiaryRepository, dst: &DiaryFileId) -> Result<()> {
+        if dst.kind() != Some(FileKind::Photo) {
+            return Ok(());
+        }
+
+        if self.auto_orient && auto_orient(&diary.file(dst))? {
+            debug!(file = %dst, "oriented photo");
+        }
+
+        if self.strip_exif {
+            strip_metadata(&diary.file(dst))?;
+        }
+
+        Ok(())
+    }
+
+    fn exec_link(&self, mut ctxt: ExecCtxt, src: PathBuf, dst: DiaryFileId) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` to `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "linking".green(),
+            src.display(),
+            dst,
+        )?;
+
+        if !ctxt.dry_run {
+            ctxt.diary.add(&src, &dst, self.link_mode())?;
+            ctxt.journal_added(&dst);
+        }
+
+        ctxt.stats.linked += 1;
+        ctxt.stats.added.push(dst);
+
+        Ok(())
+    }
+
+    fn link_mode(&self) -> AddMode {
+        if self.symlink {
+            AddMode::Symlink
+        } else {
+            AddMode::Hardlink
+        }
+    }
+
+    fn exec_overwrite(
+        &self,
+        mut ctxt: ExecCtxt,
+        src: PathBuf,
+        dst: DiaryFileId,
+        mode: AddMode,
+    ) -> Result<()> {
+        let size = Self::file_size(&src)?;
+
+        // (not available for remote diaries)
+        let existing = match fs::metadata(ctxt.diary.file(&dst)) {
+            Ok(metadata) => format!(
+                " ({}, modified {})",
+                format_size(metadata.len()),
+                DateTime::<Local>::from(metadata.modified()?).format("%Y-%m-%d %H:%M:%S"),
+            ),
+            Err(_) => String::new(),
+        };
+
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}`{} with `{}` ({})",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "overwriting".red().bold(),
+            dst,
+            existing,
+            src.display(),
+            format_size(size),
+        )?;
+
+        if !ctxt.dry_run {
+            ctxt.journal_modified(&dst)?;
+
+            let progress = ctxt.progress;
+
+            ctxt.diary
+                .add_with_progress(&src, &dst, mode, &mut |copied| {
+                    Self::report_progress(progress, copied, size)
+                })?;
+
+            if matches!(mode, AddMode::Copy | AddMode::Move) {
+                self.postprocess(ctxt.diary, &dst)?;
+            }
+
+            if mode == AddMode::Move && ctxt.journal_id.is_some() {
+                ctxt.stats.journal.push(JournalAction::Moved {
+                    src: src.clone(),
+                    dst: ctxt.diary.rel_file(&dst),
+                });
+            }
+        }
+
+        if mode == AddMode::Copy {
+            ctxt.stats.copied_bytes += size;
+        }
 
-        self.summary(env, stats)?;
+        ctxt.stats.overwritten += 1;
+        ctxt.stats.added.push(dst);
 
         Ok(())
     }
 
-    fn scan(&self, env: &mut Env) -> Result<Vec<SourceFile>> {
-        writeln!(env.stdout, "{}", "scanning".green().bold())?;
-
-        let source = SourceRepository::new(&self.source)?;
-
-        let mut files: Vec<_> = source
-            .iter()?
-            .map(|file| match file? {
-                FoundSourceFile::Recognized(file) => {
-                    if self.verbose {
-                        writeln!(env.stdout, "  {} {}", "found".green(), file.path.display())?;
-                    }
+    fn exec_convert(&self, mut ctxt: ExecCtxt, src: PathBuf, dst: DiaryFileId) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` to `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "converting".green(),
+            src.display(),
+            dst,
+        )?;
 
-                    Ok(Some(file))
-                }
+        if !ctxt.dry_run {
+            ctxt.diary.add(&src, &dst, AddMode::ConvertHeic)?;
+            self.postprocess(ctxt.diary, &dst)?;
+            ctxt.journal_added(&dst);
+        }
 
-                FoundSourceFile::Unrecognized(path) => {
-                    writeln!(
-                        env.stdout,
-                        "{} {}: unrecognized",
-                        "warn".yellow(),
-                        path.display()
-                    )?;
+        ctxt.stats.converted += 1;
+        ctxt.stats.added.push(dst);
 
-                    Ok(None)
-                }
-            })
-            .flatten_ok()
-            .filter_ok(|file| {
-                let date = file.ty.date();
+        Ok(())
+    }
 
-                let on = self.on.is_none_or(|on| date == on);
-                let from = self.from.is_none_or(|from| date >= from);
-                let to = self.to.is_none_or(|to| date <= to);
+    fn exec_thumbnail(
+        &self,
+        mut ctxt: ExecCtxt,
+        media: DiaryFileId,
+        dst: DiaryFileId,
+    ) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "creating".green(),
+            dst,
+        )?;
 
-                on && from && to
-            })
-            .collect::<Result<_>>()?;
+        if !ctxt.dry_run {
+            let kind = media.kind().context("unknown media kind")?;
+            let thumbnail =
+                generate_thumbnail(&ctxt.diary.file(&media), kind, self.thumbnail_size)?;
 
-        files.sort_by_key(|file| file.path.clone());
+            ctxt.diary.create(&dst, &thumbnail)?;
+            ctxt.journal_added(&dst);
+        }
 
-        writeln!(env.stdout)?;
+        ctxt.stats.created += 1;
 
-        Ok(files)
+        Ok(())
     }
 
-    fn plan(&self, env: &mut Env, files: &[SourceFile]) -> Result<Plan> {
-        writeln!(env.stdout, "{}", "planning".green().bold())?;
-
-        let mut plan = Plan::default();
-        let diary = DiaryRepository::new(&self.diary)?;
+    fn exec_tag(&self, mut ctxt: ExecCtxt, dst: DiaryFileId, tags: Vec<String>) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` with {}",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "tagging".green(),
+            dst,
+            tags.join(", "),
+        )?;
 
-        for file in files {
-            let steps = match &file.ty {
-                SourceFileType::Note { date } => self.plan_note(&diary, file, *date)?,
+        if !ctxt.dry_run {
+            let exists = ctxt.diary.has(&dst)?;
 
-                SourceFileType::Photo { date, id } => {
-                    self.plan_photo(&diary, file, *date, id.as_deref())?
-                }
+            let contents = if exists {
+                ctxt.journal_modified(&dst)?;
+                ctxt.diary.read(&dst)?
+            } else {
+                String::new()
+            };
 
-                SourceFileType::Video { date, id } => {
-                    self.plan_video(&diary, files, file, *date, id.as_deref())?
-                }
+            let contents = if dst.kind() == Some(FileKind::Note) {
+                add_note_tags(&contents, &tags)
+            } else {
+                add_sidecar_tags(&contents, &tags)
             };
 
-            plan.steps.extend(steps);
+            ctxt.diary.write(&dst, contents.as_bytes())?;
+
+            if !exists {
+                ctxt.journal_added(&dst);
+            }
         }
 
-        writeln!(env.stdout)?;
+        ctxt.stats.tagged += 1;
 
-        Ok(plan)
+        Ok(())
     }
 
-    fn plan_note(
+    fn exec_relink(
         &self,
-        diary: &DiaryRepository,
-        file: &SourceFile,
-        file_dt: NaiveDate,
-    ) -> Result<Vec<Step>> {
-        let dst = DiaryFileId::new(file_dt, "index.org");
+        mut ctxt: ExecCtxt,
+        dst: DiaryFileId,
+        links: Vec<(String, String)>,
+    ) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} {} in `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "relinking".green(),
+            links
+                .iter()
+                .map(|(from, to)| format!("`{}` to `{}`", from, to))
+                .join(", "),
+            dst,
+        )?;
 
-        if diary.has(&dst)? {
-            Ok(vec![Step::skip_or_remove(
-                file.path.clone(),
-                "already in the diary",
-                self.remove,
-            )])
-        } else {
-            Ok(Step::copy_and_remove(file.path.clone(), dst, self.remove).collect())
-        }
-    }
+        if !ctxt.dry_run {
+            let ext = Path::new(&dst.name)
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
 
-    fn plan_photo(
-        &self,
-        diary: &DiaryRepository,
-        file: &SourceFile,
-        file_dt: NaiveDateTime,
-        file_id: Option<&str>,
-    ) -> Result<Vec<Step>> {
-        let dst = DiaryFileId::new(
-            file_dt.date(),
-            format!(
-                "{}.{}",
-                Self::get_media_name(file, file_dt, file_id),
-                file.ext
-            ),
-        );
+            ctxt.journal_modified(&dst)?;
 
-        if diary.has(&dst)? {
-            return Ok(vec![Step::skip_or_remove(
-                file.path.clone(),
-                "already in the diary",
-                self.remove,
-            )]);
+            let contents = ctxt.diary.read(&dst)?;
+            let contents = rewrite_note_links(&contents, &ext, &links);
+
+            ctxt.diary.write(&dst, contents.as_bytes())?;
         }
 
-        Ok(Step::copy_and_remove(file.path.clone(), dst, self.remove).collect())
+        Ok(())
     }
 
-    fn plan_video(
+    fn exec_append(
         &self,
-        diary: &DiaryRepository,
-        files: &[SourceFile],
-        file: &SourceFile,
-        file_dt: NaiveDateTime,
-        file_id: Option<&str>,
-    ) -> Result<Vec<Step>> {
-        let name = Self::get_media_name(file, file_dt, file_id);
-        let mk = |ext: &str| DiaryFileId::new(file_dt.date(), format!("{}.{}", name, ext));
-
-        let dst = mk("mp4");
-        let dst_jpg = mk("jpg");
-        let dst_png = mk("png");
-        let dst_heic = mk("heic");
+        mut ctxt: ExecCtxt,
+        src: PathBuf,
+        dst: DiaryFileId,
+        contents: String,
+    ) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` to `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "appending".green(),
+            src.display(),
+            dst,
+        )?;
 
-        if diary.has(&dst)? {
-            return Ok(vec![Step::skip_or_remove(
-                file.path.clone(),
-                "already in the diary",
-                self.remove,
-            )]);
+        if !ctxt.dry_run {
+            ctxt.journal_modified(&dst)?;
+            ctxt.diary.append(&dst, contents.as_bytes())?;
         }
 
-        let has_photo = diary.has(&dst_jpg)? || diary.has(&dst_png)? || diary.has(&dst_heic)?;
-
-        let will_have_photo = file_id.is_some()
-            && files.iter().any(|src| {
-                if let SourceFileType::Photo { id: id2, .. } = &src.ty {
-                    file_id == id2.as_deref()
-                } else {
-                    false
-                }
-            });
-
-        if has_photo || will_have_photo {
-            return Ok(vec![Step::skip_or_remove(
-                file.path.clone(),
-                "already in the diary as a photo",
-                self.remove,
-            )]);
-        }
+        ctxt.stats.appended += 1;
 
-        Ok(Step::copy_and_remove(file.path.clone(), dst, self.remove).collect())
+        Ok(())
     }
 
-    fn get_media_name(file: &SourceFile, dt: NaiveDateTime, id: Option<&str>) -> String {
-        let time = format!(
-            "{:02}-{:02}-{:02}",
-            dt.time().hour(),
-            dt.time().minute(),
-            dt.time().second()
-        );
+    fn exec_merge(
+        &self,
+        mut ctxt: ExecCtxt,
+        srcs: Vec<PathBuf>,
+        dst: DiaryFileId,
+        contents: String,
+    ) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} {} into `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "merging".green(),
+            srcs.iter()
+                .map(|src| format!("`{}`", src.display()))
+                .join(", "),
+            dst,
+        )?;
 
-        if let Some(id) = id {
-            return format!("{} {}", time, id);
-        }
+        if !ctxt.dry_run {
+            // (planning has already consulted `--on-conflict`)
+            let exists = ctxt.diary.has(&dst)?;
 
-        if file.stem.starts_with("Screenshot") {
-            return format!("{} screenshot", time);
-        }
+            if exists {
+                ctxt.journal_modified(&dst)?;
+            }
 
-        if file.stem.starts_with("Screencast") || file.stem.starts_with("Screen Recording") {
-            return format!("{} screencast", time);
-        }
+            ctxt.diary.write(&dst, contents.as_bytes())?;
 
-        if file.stem.starts_with("Recording") {
-            return format!("{} recording", time);
+            if !exists {
+                ctxt.journal_added(&dst);
+            }
         }
 
-        file.stem.to_owned()
-    }
-
-    fn exec(&self, env: &mut Env, plan: Plan) -> Result<Stats> {
-        writeln!(env.stdout, "{}", "executing".green().bold())?;
+        ctxt.stats.merged += srcs.len();
+        ctxt.stats.added.push(dst);
 
-        let mut diary = DiaryRepository::new(&self.diary)?;
-        let mut stats = Stats::default();
-        let step_count = plan.steps.len();
+        Ok(())
+    }
 
-        for (step_idx, step) in plan.steps.into_iter().enumerate() {
-            let ctxt = ExecCtxt {
-                env,
-                stats: &mut stats,
-                diary: &mut diary,
-                step_idx,
-                step_count,
-            };
+    fn exec_create(&self, mut ctxt: ExecCtxt, dst: DiaryFileId, contents: String) -> Result<()> {
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}`",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "creating".green(),
+            dst,
+        )?;
 
-            match step {
-                Step::Copy { src, dst } => {
-                    self.exec_copy(ctxt, src, dst)?;
-                }
-                Step::Skip { src, reason } => {
-                    self.exec_skip(ctxt, src, reason)?;
-                }
-                Step::Remove { src, reason } => {
-                    self.exec_remove(ctxt, src, reason)?;
-                }
-            }
+        if !ctxt.dry_run {
+            ctxt.diary.create(&dst, contents.as_bytes())?;
+            ctxt.journal_added(&dst);
         }
 
-        Ok(stats)
+        ctxt.stats.created += 1;
+
+        Ok(())
     }
 
-    fn exec_copy(&self, ctxt: ExecCtxt, src: PathBuf, dst: DiaryFileId) -> Result<()> {
+    fn exec_quarantine(&self, ctxt: ExecCtxt, src: PathBuf, dst: PathBuf) -> Result<()> {
         writeln!(
             ctxt.env.stdout,
             "  {}/{}: {} `{}` to `{}`",
             ctxt.step_idx + 1,
             ctxt.step_count,
-            "copying".green(),
+            "quarantining".green(),
             src.display(),
-            dst,
+            dst.display(),
         )?;
 
-        if !self.dry_run {
-            ctxt.diary.add(&src, &dst)?;
+        if !ctxt.dry_run {
+            if dst.try_exists()? {
+                return Err(anyhow!(
+                    "cannot quarantine `{}`, because it would overwrite `{}`",
+                    src.display(),
+                    dst.display(),
+                ));
+            }
+
+            if let Some(dir) = dst.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+            }
+
+            fs::copy(&src, &dst).with_context(|| {
+                format!("couldn't copy `{}` to `{}`", src.display(), dst.display())
+            })?;
         }
 
-        ctxt.stats.copied += 1;
+        ctxt.stats.quarantined += 1;
 
         Ok(())
     }
@@ -317,6 +2696,10 @@ impl AddCmd {
     }
 
     fn exec_remove(&self, ctxt: ExecCtxt, src: PathBuf, reason: String) -> Result<()> {
+        if let Some(trash_dir) = &self.trash_dir {
+            return self.exec_trash(ctxt, src, trash_dir, reason);
+        }
+
         writeln!(
             ctxt.env.stdout,
             "  {}/{}: {} `{}` ({})",
@@ -327,17 +2710,82 @@ impl AddCmd {
             reason,
         )?;
 
-        if !self.dry_run {
-            fs::remove_file(&src).with_context(|| format!("couldn't remove: {}", src.display()))?;
+        let size = Self::file_size(&src)?;
+
+        if !ctxt.dry_run {
+            if let Some(journal_id) = ctxt.journal_id {
+                // Keep the file around, so that `diary undo` can restore it
+                let name = format!(
+                    "{}/{}-{}",
+                    journal_id,
+                    ctxt.step_idx + 1,
+                    src.file_name()
+                        .context("source file has no name")?
+                        .to_string_lossy(),
+                );
+
+                let trash = ctxt.diary.trash(&src, &name)?;
+
+                ctxt.stats
+                    .journal
+                    .push(JournalAction::Trashed { src, trash });
+            } else {
+                fs::remove_file(&src)
+                    .with_context(|| format!("couldn't remove: {}", src.display()))?;
+            }
         }
 
         ctxt.stats.removed += 1;
+        ctxt.stats.removed_bytes += size;
 
         Ok(())
     }
 
-    fn summary(&self, env: &mut Env, stats: Stats) -> Result<()> {
-        writeln!(env.stdout)?;
+    /// Moves source file into `--trash-dir` instead of removing it.
+    fn exec_trash(
+        &self,
+        ctxt: ExecCtxt,
+        src: PathBuf,
+        trash_dir: &Path,
+        reason: String,
+    ) -> Result<()> {
+        let dst = trash_dir.join(self.source_rel_path(&src));
+
+        writeln!(
+            ctxt.env.stdout,
+            "  {}/{}: {} `{}` to `{}` ({})",
+            ctxt.step_idx + 1,
+            ctxt.step_count,
+            "trashing".green(),
+            src.display(),
+            dst.display(),
+            reason,
+        )?;
+
+        if !ctxt.dry_run {
+            if dst.try_exists()? {
+                return Err(anyhow!(
+                    "cannot trash `{}`, because it would overwrite `{}`",
+                    src.display(),
+                    dst.display(),
+                ));
+            }
+
+            move_file(&src, &dst)?;
+        }
+
+        ctxt.stats.trashed += 1;
+
+        Ok(())
+    }
+
+    fn file_size(path: &Path) -> Result<u64> {
+        Ok(fs::metadata(path)
+            .with_context(|| format!("couldn't read metadata: {}", path.display()))?
+            .len())
+    }
+
+    fn summary(&self, env: &mut Env, stats: &Stats, elapsed: Duration) -> Result<()> {
         writeln!(env.stdout, "{}", "summary".green().bold())?;
 
         let mut print_files_stats = |files: usize, verb: &str| -> Result<()> {
@@ -356,32 +2804,273 @@ impl AddCmd {
 
         print_files_stats(stats.skipped, "skipped")?;
         print_files_stats(stats.copied, "copied")?;
+        print_files_stats(stats.moved, "moved")?;
+        print_files_stats(stats.linked, "linked")?;
+        print_files_stats(stats.converted, "converted")?;
+        print_files_stats(stats.overwritten, "overwritten")?;
+        print_files_stats(stats.appended, "appended")?;
+        print_files_stats(stats.merged, "merged")?;
+        print_files_stats(stats.tagged, "tagged")?;
+        print_files_stats(stats.created, "created")?;
+        print_files_stats(stats.quarantined, "quarantined")?;
         print_files_stats(stats.removed, "removed")?;
+        print_files_stats(stats.trashed, "trashed")?;
+        print_files_stats(stats.failed, "couldn't identify")?;
+        print_files_stats(stats.too_large, "ignored (too large)")?;
+
+        if stats.not_writable > 0 {
+            writeln!(
+                env.stdout,
+                "  {} {} not writable",
+                stats.not_writable,
+                if stats.not_writable > 1 {
+                    "directories"
+                } else {
+                    "directory"
+                },
+            )?;
+        }
+
+        let bytes: Vec<_> = [
+            (stats.copied_bytes, "copied"),
+            (stats.moved_bytes, "moved"),
+            (stats.removed_bytes, "removed"),
+        ]
+        .into_iter()
+        .filter(|(bytes, _)| *bytes > 0)
+        .map(|(bytes, verb)| format!("{} {}", format_size(bytes), verb))
+        .collect();
+
+        if !bytes.is_empty() {
+            writeln!(env.stdout, "  {}", bytes.join(", "))?;
+        }
+
+        writeln!(env.stdout, "  took {:.1?}", elapsed)?;
 
         Ok(())
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize)]
 struct Stats {
     skipped: usize,
     copied: usize,
+    moved: usize,
+    linked: usize,
+    converted: usize,
+    overwritten: usize,
+    appended: usize,
+    merged: usize,
+    tagged: usize,
+    created: usize,
+    quarantined: usize,
     removed: usize,
+    trashed: usize,
+    failed: usize,
+    too_large: usize,
+    not_writable: usize,
+    copied_bytes: u64,
+    moved_bytes: u64,
+    removed_bytes: u64,
+
+    /// Files that have been added into the diary
+    #[serde(skip)]
+    added: Vec<DiaryFileId>,
+
+    /// Files that couldn't be added into the diary (and so mustn't be removed)
+    #[serde(skip)]
+    not_added: HashSet<PathBuf>,
+
+    /// Changes to record in the journal (see `--journal`)
+    #[serde(skip)]
+    journal: Vec<JournalAction>,
+}
+
+impl Stats {
+    fn has_changes(&self) -> bool {
+        self.copied > 0
+            || self.moved > 0
+            || self.linked > 0
+            || self.converted > 0
+            || self.overwritten > 0
+            || self.appended > 0
+            || self.merged > 0
+            || self.tagged > 0
+            || self.created > 0
+            || self.quarantined > 0
+            || self.removed > 0
+            || self.trashed > 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Interactive {
+    /// Print the plan and ask once whether to execute it
+    Plan,
+
+    /// Ask about each file that's about to be added
+    Each,
+}
+
+enum Answer {
+    Yes,
+    No,
+    All,
+}
+
+/// Order in which files get processed (see `--sort`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SortBy {
+    /// By path
+    Name,
+
+    /// By date, notes going before media of the same day
+    Date,
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .map_err(|_| format!("invalid time (expected HH:MM): {}", s))
+}
+
+enum PlannedDst {
+    /// File can be added under this name
+    Free(DiaryFileId),
+
+    /// File shouldn't be added - here's what to do with it instead
+    Taken(Step),
+}
+
+/// Files linked from notes (see `--copy-note-assets`).
+#[derive(Default)]
+struct NoteAssets {
+    /// Note -> its links, together with files they point at
+    links: HashMap<PathBuf, Vec<(String, PathBuf)>>,
+
+    /// Canonical paths of all the linked files
+    files: HashSet<PathBuf>,
+}
+
+impl NoteAssets {
+    fn contains(&self, path: &Path) -> bool {
+        !self.files.is_empty()
+            && fs::canonicalize(path).is_ok_and(|path| self.files.contains(&path))
+    }
 }
 
-#[derive(Default, Debug)]
+struct Sources {
+    files: Vec<SourceFile>,
+    unrecognized: Vec<PathBuf>,
+    failed: usize,
+    too_large: usize,
+    scanned_at: DateTime<Local>,
+}
+
+#[derive(Clone, Default, Debug, Serialize)]
 struct Plan {
     steps: Vec<Step>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 enum Step {
-    Copy { src: PathBuf, dst: DiaryFileId },
-    Skip { src: PathBuf, reason: String },
-    Remove { src: PathBuf, reason: String },
+    Copy {
+        src: PathBuf,
+        dst: DiaryFileId,
+    },
+    Move {
+        src: PathBuf,
+        dst: DiaryFileId,
+    },
+    Link {
+        src: PathBuf,
+        dst: DiaryFileId,
+    },
+    Convert {
+        src: PathBuf,
+        dst: DiaryFileId,
+    },
+    /// Adds file in place of an existing one (see `--on-conflict overwrite`)
+    Overwrite {
+        src: PathBuf,
+        dst: DiaryFileId,
+        mode: AddMode,
+    },
+    /// Generates thumbnail for media file added from `src` (see
+    /// `--thumbnails`)
+    Thumbnail {
+        src: PathBuf,
+        media: DiaryFileId,
+        dst: DiaryFileId,
+    },
+    /// Adds tags into an org note or a `.tags` sidecar (see `--tag`)
+    Tag {
+        src: PathBuf,
+        dst: DiaryFileId,
+        tags: Vec<String>,
+    },
+    /// Points note's links at files added together with it (see
+    /// `--copy-note-assets`)
+    Relink {
+        src: PathBuf,
+        dst: DiaryFileId,
+        links: Vec<(String, String)>,
+    },
+    Append {
+        src: PathBuf,
+        dst: DiaryFileId,
+        contents: String,
+    },
+    /// Creates a note out of many notes of the same day (see `--merge-notes`)
+    Merge {
+        srcs: Vec<PathBuf>,
+        dst: DiaryFileId,
+        contents: String,
+    },
+    Create {
+        dst: DiaryFileId,
+        contents: String,
+    },
+    Quarantine {
+        src: PathBuf,
+        dst: PathBuf,
+    },
+    Skip {
+        src: PathBuf,
+        reason: String,
+    },
+    Remove {
+        src: PathBuf,
+        reason: String,
+    },
 }
 
 impl Step {
+    /// Returns file this step writes into the diary, if any.
+    fn dst(&self) -> Option<&DiaryFileId> {
+        match self {
+            Step::Copy { dst, .. }
+            | Step::Move { dst, .. }
+            | Step::Link { dst, .. }
+            | Step::Convert { dst, .. }
+            | Step::Overwrite { dst, .. }
+            | Step::Thumbnail { dst, .. }
+            | Step::Tag { dst, .. }
+            | Step::Relink { dst, .. }
+            | Step::Append { dst, .. }
+            | Step::Merge { dst, .. }
+            | Step::Create { dst, .. } => Some(dst),
+
+            Step::Quarantine { .. } | Step::Skip { .. } | Step::Remove { .. } => None,
+        }
+    }
+
     fn copy_and_remove(src: PathBuf, dst: DiaryFileId, remove: bool) -> impl Iterator<Item = Self> {
         let add = Step::Copy {
             src: src.clone(),
@@ -413,4 +3102,63 @@ struct ExecCtxt<'a, 'b> {
     diary: &'a mut DiaryRepository,
     step_idx: usize,
     step_count: usize,
+    dry_run: bool,
+
+    /// Identifier of this import within the journal (see `--journal`), also
+    /// naming the directory removed source files get trashed into
+    journal_id: Option<&'a str>,
+
+    progress: Option<&'a ProgressBar>,
+}
+
+impl ExecCtxt<'_, '_> {
+    /// Records that given file has been created in the diary, so that `diary
+    /// undo` can remove it.
+    fn journal_added(&mut self, dst: &DiaryFileId) {
+        if self.journal_id.is_some() {
+            self.stats.journal.push(JournalAction::Added {
+                dst: self.diary.rel_file(dst),
+            });
+        }
+    }
+
+    /// Keeps the current version of given diary file in the trash before it
+    /// gets changed, so that `diary undo` can bring it back; files that this
+    /// import has already added or kept are not kept again, since reverting the
+    /// earlier action covers them.
+    fn journal_modified(&mut self, dst: &DiaryFileId) -> Result<()> {
+        let Some(journal_id) = self.journal_id else {
+            return Ok(());
+        };
+
+        let rel_dst = self.diary.rel_file(dst);
+
+        let seen = self.stats.journal.iter().any(|action| match action {
+            JournalAction::Added { dst } | JournalAction::Modified { dst, .. } => *dst == rel_dst,
+            _ => false,
+        });
+
+        if seen {
+            return Ok(());
+        }
+
+        let name = format!(
+            "{}/{}-{}",
+            journal_id,
+            self.step_idx + 1,
+            rel_dst
+                .file_name()
+                .context("diary file has no name")?
+                .to_string_lossy(),
+        );
+
+        let backup = self.diary.backup(dst, &name)?;
+
+        self.stats.journal.push(JournalAction::Modified {
+            dst: rel_dst,
+            backup,
+        });
+
+        Ok(())
+    }
 }
@@ -0,0 +1,117 @@
+use crate::utils::{parse_media_name, DiaryArgs, DiaryFileId, FileKind, FoundDiaryFile};
+use crate::Env;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use clap::Parser;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Parser)]
+pub struct CheckCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+}
+
+impl CheckCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let diary = self.diary.open(&env.config)?;
+        let mut problems = Vec::new();
+        let mut days: BTreeMap<NaiveDate, Day> = BTreeMap::new();
+
+        writeln!(env.stdout, "{}", "checking".green().bold())?;
+
+        for file in diary.iter()? {
+            let id = match file? {
+                FoundDiaryFile::Recognized(id) => id,
+
+                FoundDiaryFile::Unrecognized(path) => {
+                    problems.push(format!("`{}`: not in a day directory", path.display()));
+                    continue;
+                }
+            };
+
+            let day = days.entry(id.date).or_default();
+
+            match id.kind() {
+                Some(FileKind::Note) => {
+                    day.has_note = true;
+
+                    if let Some(date) = Self::note_date(&diary.file(&id))? {
+                        if date != id.date {
+                            problems.push(Self::misplaced(&id, date));
+                        }
+                    }
+                }
+
+                Some(FileKind::Photo | FileKind::Video | FileKind::Audio) => {
+                    day.has_media = true;
+
+                    let stem = Path::new(&id.name)
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .unwrap_or_default();
+
+                    if let Ok(Some((date, _))) = parse_media_name(stem) {
+                        if date.date() != id.date {
+                            problems.push(Self::misplaced(&id, date.date()));
+                        }
+                    }
+                }
+
+                None => (),
+            }
+        }
+
+        for (date, day) in days {
+            if day.has_media && !day.has_note {
+                problems.push(format!("{}: has media, but no note", date));
+            }
+        }
+
+        for problem in &problems {
+            writeln!(env.stdout, "  {} {}", "problem".yellow(), problem)?;
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        if problems.is_empty() {
+            writeln!(env.stdout, "  no problems found")?;
+
+            Ok(())
+        } else {
+            writeln!(env.stdout, "  {} problem(s) found", problems.len())?;
+
+            Err(anyhow!("diary has {} problem(s)", problems.len()))
+        }
+    }
+
+    fn misplaced(id: &DiaryFileId, date: NaiveDate) -> String {
+        format!(
+            "`{}`: refers to {}, but is stored under {}",
+            id, date, id.date
+        )
+    }
+
+    /// Returns the date mentioned in note's first line, if any - e.g.
+    /// `#+TITLE: 2018-01-02`.
+    fn note_date(path: &Path) -> Result<Option<NaiveDate>> {
+        let note = fs::read_to_string(path)?;
+        let line = note.lines().next().unwrap_or_default();
+
+        let date = line
+            .char_indices()
+            .filter_map(|(idx, _)| line.get(idx..idx + 10))
+            .find_map(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+
+        Ok(date)
+    }
+}
+
+#[derive(Default)]
+struct Day {
+    has_note: bool,
+    has_media: bool,
+}
@@ -0,0 +1,121 @@
+use crate::utils::{format_size, DiaryArgs, DiaryFileId, FileKind, FoundDiaryFile};
+use crate::Env;
+use anyhow::Result;
+use clap::Parser;
+use colored::Colorize;
+use std::collections::HashMap;
+
+#[derive(Debug, Parser)]
+pub struct DedupCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    apply: bool,
+}
+
+impl DedupCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let mut diary = self.diary.open(&env.config)?;
+
+        writeln!(env.stdout, "{}", "scanning".green().bold())?;
+
+        let mut files: Vec<_> = diary
+            .iter()?
+            .filter_map(|file| match file {
+                Ok(FoundDiaryFile::Recognized(id)) => Some(Ok(id)),
+                Ok(FoundDiaryFile::Unrecognized(_)) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<_>>()?;
+
+        files.retain(|id| {
+            matches!(
+                id.kind(),
+                Some(FileKind::Photo | FileKind::Video | FileKind::Audio)
+            )
+        });
+
+        files.sort_by(|a, b| (a.date, &a.name).cmp(&(b.date, &b.name)));
+
+        let mut groups: HashMap<_, Vec<DiaryFileId>> = HashMap::new();
+        let mut order = Vec::new();
+
+        for file in files {
+            let hash = diary.hash(&file)?;
+            let group = groups.entry(hash).or_default();
+
+            if group.is_empty() {
+                order.push(hash);
+            }
+
+            group.push(file);
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "duplicates".green().bold())?;
+
+        let mut duplicates = 0;
+        let mut reclaimable = 0;
+
+        for hash in order {
+            let Some((original, copies)) = groups[&hash].split_first() else {
+                continue;
+            };
+
+            if copies.is_empty() {
+                continue;
+            }
+
+            writeln!(env.stdout, "  `{}`", original)?;
+
+            for copy in copies {
+                duplicates += 1;
+                reclaimable += diary.file(copy).metadata()?.len();
+
+                if self.apply {
+                    writeln!(env.stdout, "    {} `{}`", "removing".green(), copy)?;
+                    diary.remove(copy)?;
+
+                    // Don't leave the copy's thumbnail and tags orphaned
+                    let thumb = diary.thumbnail_id(copy);
+
+                    if diary.has(&thumb)? {
+                        diary.remove(&thumb)?;
+                    }
+
+                    let tags = diary.tags_id(copy);
+
+                    if diary.has(&tags)? {
+                        diary.remove(&tags)?;
+                    }
+                } else {
+                    writeln!(env.stdout, "    same as `{}`", copy)?;
+                }
+            }
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        if duplicates == 0 {
+            writeln!(env.stdout, "  no duplicates found")?;
+        } else if self.apply {
+            writeln!(
+                env.stdout,
+                "  removed {} duplicate(s), reclaimed {}",
+                duplicates,
+                format_size(reclaimable)
+            )?;
+        } else {
+            writeln!(
+                env.stdout,
+                "  found {} duplicate(s), {} can be reclaimed with `--apply`",
+                duplicates,
+                format_size(reclaimable)
+            )?;
+        }
+
+        Ok(())
+    }
+}
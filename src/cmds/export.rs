@@ -0,0 +1,284 @@
+use crate::utils::{
+    generate_thumbnail, DateFilter, DiaryArgs, DiaryFileId, DiaryRepository, FileKind,
+    FoundDiaryFile,
+};
+use crate::Env;
+use anyhow::{Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::Local;
+use clap::Parser;
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body { font-family: sans-serif; max-width: 960px; margin: 0 auto; padding: 1em; }
+pre { white-space: pre-wrap; }
+figure { display: inline-block; margin: 0.5em; text-align: center; }
+figcaption { font-size: small; color: gray; }
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{body}</body>
+</html>
+"#;
+
+#[derive(Debug, Parser)]
+pub struct ExportCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(flatten)]
+    filter: DateFilter,
+
+    #[clap(long)]
+    #[clap(required_unless_present = "zip")]
+    out: Option<PathBuf>,
+
+    #[clap(long)]
+    #[clap(default_value_t = 256)]
+    thumbnail_size: u32,
+
+    #[clap(long)]
+    #[clap(conflicts_with = "out")]
+    zip: Option<PathBuf>,
+
+    #[clap(long)]
+    #[clap(requires = "zip")]
+    dry_run: bool,
+}
+
+impl ExportCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let diary = self.diary.open(&env.config)?;
+
+        let mut files: Vec<_> = diary
+            .iter()?
+            .filter_map_ok(|file| match file {
+                FoundDiaryFile::Recognized(id) => Some(id),
+                FoundDiaryFile::Unrecognized(_) => None,
+            })
+            .filter_ok(|id| self.filter.matches(id.date))
+            .collect::<Result<_>>()?;
+
+        // (notes go first, so that photos illustrate them)
+        files.sort_by_key(|id| (id.date, id.kind() != Some(FileKind::Note), id.name.clone()));
+
+        match (&self.out, &self.zip) {
+            (_, Some(zip)) => self.export_zip(env, &diary, &files, zip),
+            (Some(out), _) => self.export_html(env, &diary, &files, out),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    fn export_html(
+        &self,
+        env: &mut Env,
+        diary: &DiaryRepository,
+        files: &[DiaryFileId],
+        out: &Path,
+    ) -> Result<()> {
+        writeln!(env.stdout, "{}", "exporting".green().bold())?;
+
+        let mut body = String::new();
+        let mut days = 0;
+
+        for files in files.chunk_by(|a, b| a.date == b.date) {
+            let date = files[0].date;
+
+            writeln!(env.stdout, "  {} {}", "adding".green(), date)?;
+            writeln!(body, "<section>\n<h2>{}</h2>", date)?;
+
+            for file in files {
+                match file.kind() {
+                    Some(FileKind::Note) => {
+                        writeln!(body, "<pre>{}</pre>", escape(diary.read(file)?.trim_end()))?;
+                    }
+
+                    Some(kind @ (FileKind::Photo | FileKind::Video)) => {
+                        let thumbnail = match self.thumbnail(diary, file, kind) {
+                            Ok(thumbnail) => thumbnail,
+
+                            Err(err) => {
+                                writeln!(env.stderr, "{} {}: {:#}", "warn".yellow(), file, err)?;
+
+                                continue;
+                            }
+                        };
+
+                        writeln!(
+                            body,
+                            "<figure><img src=\"data:image/jpeg;base64,{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>",
+                            BASE64_STANDARD.encode(thumbnail),
+                            escape(&file.name),
+                            escape(&file.name),
+                        )?;
+                    }
+
+                    _ => (),
+                }
+            }
+
+            writeln!(body, "</section>")?;
+            days += 1;
+        }
+
+        let title = match (files.first(), files.last()) {
+            (Some(first), Some(last)) if first.date != last.date => {
+                format!("{} – {}", first.date, last.date)
+            }
+            (Some(first), _) => first.date.to_string(),
+            _ => "diary".into(),
+        };
+
+        #[allow(clippy::literal_string_with_formatting_args)]
+        let html = TEMPLATE
+            .replace("{title}", &escape(&title))
+            .replace("{body}", &body);
+
+        fs::write(out, html).with_context(|| format!("couldn't write: {}", out.display()))?;
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        writeln!(
+            env.stdout,
+            "  exported {} {} into `{}`",
+            days,
+            if days == 1 { "day" } else { "days" },
+            out.display()
+        )?;
+
+        Ok(())
+    }
+
+    /// Archives given files (together with their thumbnails), keeping the
+    /// diary's directory structure.
+    ///
+    /// Entries are stored without compression, since media files are already
+    /// compressed anyway.
+    fn export_zip(
+        &self,
+        env: &mut Env,
+        diary: &DiaryRepository,
+        files: &[DiaryFileId],
+        zip: &Path,
+    ) -> Result<()> {
+        if self.dry_run {
+            writeln!(env.stdout, "{} is active", "--dry-run".yellow())?;
+            writeln!(env.stdout)?;
+        }
+
+        writeln!(env.stdout, "{}", "archiving".green().bold())?;
+
+        let mut archive = if self.dry_run {
+            None
+        } else {
+            let file =
+                File::create(zip).with_context(|| format!("couldn't create: {}", zip.display()))?;
+
+            Some(ZipWriter::new(file))
+        };
+
+        let mut archived = 0;
+
+        for file in files {
+            let thumbnail = diary.thumbnail_id(file);
+            let thumbnail = diary.has(&thumbnail)?.then_some(thumbnail);
+
+            for file in [Some(file), thumbnail.as_ref()].into_iter().flatten() {
+                writeln!(env.stdout, "  {} `{}`", "adding".green(), file)?;
+
+                if let Some(archive) = &mut archive {
+                    Self::archive(archive, diary, file)
+                        .with_context(|| format!("couldn't archive `{}`", file))?;
+                }
+
+                archived += 1;
+            }
+        }
+
+        if let Some(archive) = archive {
+            archive
+                .finish()
+                .with_context(|| format!("couldn't write: {}", zip.display()))?;
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        writeln!(
+            env.stdout,
+            "  {} {} {} into `{}`",
+            if self.dry_run {
+                "would archive"
+            } else {
+                "archived"
+            },
+            archived,
+            if archived == 1 { "file" } else { "files" },
+            zip.display()
+        )?;
+
+        Ok(())
+    }
+
+    fn archive(
+        archive: &mut ZipWriter<File>,
+        diary: &DiaryRepository,
+        id: &DiaryFileId,
+    ) -> Result<()> {
+        let path = diary.file(id);
+        let metadata = fs::metadata(&path)?;
+
+        let mtime = chrono::DateTime::<Local>::from(metadata.modified()?).naive_local();
+
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(mtime.try_into().unwrap_or_default())
+            .large_file(metadata.len() > u32::MAX as u64);
+
+        archive.start_file(diary.rel_file(id).to_string_lossy(), options)?;
+
+        io::copy(&mut File::open(&path)?, archive)?;
+
+        Ok(())
+    }
+
+    /// Returns JPEG thumbnail of given photo or video - the one generated by
+    /// `add --thumbnails`, if it exists, or a freshly generated one.
+    fn thumbnail(
+        &self,
+        diary: &DiaryRepository,
+        id: &DiaryFileId,
+        kind: FileKind,
+    ) -> Result<Vec<u8>> {
+        let thumbnail = diary.thumbnail_id(id);
+
+        if diary.has(&thumbnail)? {
+            let path = diary.file(&thumbnail);
+
+            return fs::read(&path).with_context(|| format!("couldn't read: {}", path.display()));
+        }
+
+        generate_thumbnail(&diary.file(id), kind, self.thumbnail_size)
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,96 @@
+use crate::utils::Layout;
+use crate::{Config, Env};
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+pub struct InitCmd {
+    #[clap(long)]
+    diary: PathBuf,
+
+    #[clap(long)]
+    with_config: bool,
+}
+
+impl InitCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        writeln!(env.stdout, "{}", "initializing".green().bold())?;
+
+        if self.diary.try_exists()? {
+            self.check_empty()?;
+        } else {
+            writeln!(
+                env.stdout,
+                "  {} `{}`",
+                "creating".green(),
+                self.diary.display()
+            )?;
+
+            fs::create_dir_all(&self.diary)
+                .with_context(|| format!("couldn't create directory: {}", self.diary.display()))?;
+        }
+
+        let gitkeep = self.diary.join(".gitkeep");
+
+        if !gitkeep.try_exists()? {
+            Self::write(env, &gitkeep, "")?;
+        }
+
+        if self.with_config {
+            let path = self.diary.join(Config::FILE_NAME);
+
+            if path.try_exists()? {
+                return Err(anyhow!("config already exists: {}", path.display()));
+            }
+
+            let config = Config {
+                diary: Some(fs::canonicalize(&self.diary)?),
+                layout: Some(Layout::default()),
+                ..Default::default()
+            };
+
+            Self::write(env, &path, &toml::to_string(&config)?)?;
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+        writeln!(env.stdout, "  diary is ready")?;
+
+        Ok(())
+    }
+
+    fn check_empty(&self) -> Result<()> {
+        for entry in fs::read_dir(&self.diary)
+            .with_context(|| format!("couldn't read directory: {}", self.diary.display()))?
+        {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            let is_year = name.len() == 4
+                && name.chars().all(|ch| ch.is_ascii_digit())
+                && entry.path().is_dir();
+
+            if is_year {
+                return Err(anyhow!(
+                    "refusing to initialize `{}`, because it already contains a diary (found `{}`)",
+                    self.diary.display(),
+                    name,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(env: &mut Env, path: &Path, contents: &str) -> Result<()> {
+        writeln!(env.stdout, "  {} `{}`", "creating".green(), path.display())?;
+
+        fs::write(path, contents).with_context(|| format!("couldn't write: {}", path.display()))?;
+
+        Ok(())
+    }
+}
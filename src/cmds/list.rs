@@ -0,0 +1,107 @@
+use crate::utils::{DateFilter, DiaryArgs, FileKind, FoundDiaryFile};
+use crate::Env;
+use anyhow::Result;
+use clap::Parser;
+use colored::Colorize;
+use itertools::Itertools;
+
+#[derive(Debug, Parser)]
+pub struct ListCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(flatten)]
+    filter: DateFilter,
+}
+
+impl ListCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let diary = self.diary.open(&env.config)?;
+
+        let mut files: Vec<_> = diary
+            .iter()?
+            .map(|file| match file? {
+                FoundDiaryFile::Recognized(id) => Ok(Some(id)),
+
+                FoundDiaryFile::Unrecognized(path) => {
+                    writeln!(
+                        env.stderr,
+                        "{} {}: unrecognized",
+                        "warn".yellow(),
+                        path.display()
+                    )?;
+
+                    Ok(None)
+                }
+            })
+            .flatten_ok()
+            .filter_ok(|id| self.filter.matches(id.date))
+            .collect::<Result<_>>()?;
+
+        files.sort_by(|a, b| (a.date, &a.name).cmp(&(b.date, &b.name)));
+
+        for files in files.chunk_by(|a, b| a.date == b.date) {
+            let mut stats = Stats::default();
+
+            for file in files {
+                match file.kind() {
+                    Some(FileKind::Note) => stats.notes += 1,
+                    Some(FileKind::Photo) => stats.photos += 1,
+                    Some(FileKind::Video) => stats.videos += 1,
+                    Some(FileKind::Audio) => stats.audios += 1,
+                    None => stats.others += 1,
+                }
+            }
+
+            writeln!(
+                env.stdout,
+                "{} ({})",
+                files[0].date.to_string().green().bold(),
+                stats
+            )?;
+
+            for file in files {
+                let kind = match file.kind() {
+                    Some(FileKind::Note) => "note",
+                    Some(FileKind::Photo) => "photo",
+                    Some(FileKind::Video) => "video",
+                    Some(FileKind::Audio) => "audio",
+                    None => "other",
+                };
+
+                writeln!(env.stdout, "  {} {}", kind.green(), file.name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    notes: usize,
+    photos: usize,
+    videos: usize,
+    audios: usize,
+    others: usize,
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = [
+            (self.notes, "note"),
+            (self.photos, "photo"),
+            (self.videos, "video"),
+            (self.audios, "audio file"),
+            (self.others, "other file"),
+        ];
+
+        let counts = counts
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, noun)| format!("{} {}{}", count, noun, if count > 1 { "s" } else { "" }))
+            .join(", ");
+
+        write!(f, "{}", counts)
+    }
+}
@@ -0,0 +1,121 @@
+use crate::utils::{DiaryArgs, DiaryFileId, FoundDiaryFile};
+use crate::Env;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use clap::Parser;
+use colored::Colorize;
+use glob::Pattern;
+use itertools::Itertools;
+use std::collections::HashSet;
+
+#[derive(Debug, Parser)]
+pub struct MoveCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    from_date: NaiveDate,
+
+    #[clap(long)]
+    to_date: NaiveDate,
+
+    #[clap(long)]
+    #[clap(value_parser = Pattern::new)]
+    name: Vec<Pattern>,
+
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl MoveCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let mut diary = self.diary.open(&env.config)?;
+
+        if self.dry_run {
+            writeln!(env.stdout, "{} is active", "--dry-run".yellow())?;
+            writeln!(env.stdout)?;
+        }
+
+        writeln!(env.stdout, "{}", "moving".green().bold())?;
+
+        let mut files: Vec<_> = diary
+            .iter()?
+            .filter_map_ok(|file| match file {
+                FoundDiaryFile::Recognized(id) => Some(id),
+                FoundDiaryFile::Unrecognized(_) => None,
+            })
+            .filter_ok(|id| id.date == self.from_date)
+            .filter_ok(|id| {
+                self.name.is_empty() || self.name.iter().any(|name| name.matches(&id.name))
+            })
+            .collect::<Result<_>>()?;
+
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Tag sidecars are moved together with their files (see below), so
+        // they're not moved on their own, even if `--name` matches them
+        let sidecars: HashSet<_> = files.iter().map(|id| diary.tags_id(id).name).collect();
+
+        files.retain(|id| !sidecars.contains(&id.name));
+
+        // Check all destinations up front, so that we don't end up with only
+        // some of the files moved
+        let moves: Vec<(DiaryFileId, DiaryFileId)> = files
+            .into_iter()
+            .map(|src| {
+                let dst = diary.file_id(self.to_date, &src.name);
+
+                // (the file's tag sidecar mustn't overwrite anything either)
+                let overwrites_tags =
+                    diary.has(&diary.tags_id(&src))? && diary.has(&diary.tags_id(&dst))?;
+
+                if diary.has(&dst)? || overwrites_tags {
+                    return Err(anyhow!(
+                        "cannot move `{}`, because it would overwrite `{}`",
+                        src,
+                        dst
+                    ));
+                }
+
+                Ok((src, dst))
+            })
+            .collect::<Result<_>>()?;
+
+        for (src, dst) in &moves {
+            writeln!(env.stdout, "  {} `{}` to `{}`", "moving".green(), src, dst)?;
+
+            if !self.dry_run {
+                diary.rename(src, dst)?;
+
+                let src_thumb = diary.thumbnail_id(src);
+
+                if diary.has(&src_thumb)? {
+                    diary.rename(&src_thumb, &diary.thumbnail_id(dst))?;
+                }
+
+                let src_tags = diary.tags_id(src);
+
+                if diary.has(&src_tags)? {
+                    diary.rename(&src_tags, &diary.tags_id(dst))?;
+                }
+            }
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        if moves.is_empty() {
+            writeln!(env.stdout, "  no matching files found")?;
+        } else {
+            writeln!(
+                env.stdout,
+                "  {} {} file{}",
+                if self.dry_run { "would move" } else { "moved" },
+                moves.len(),
+                if moves.len() > 1 { "s" } else { "" }
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use crate::utils::DiaryArgs;
+use crate::Env;
+use anyhow::Result;
+use clap::Parser;
+use colored::Colorize;
+
+#[derive(Debug, Parser)]
+pub struct PruneCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl PruneCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let mut diary = self.diary.open(&env.config)?;
+
+        if self.dry_run {
+            writeln!(env.stdout, "{} is active", "--dry-run".yellow())?;
+            writeln!(env.stdout)?;
+        }
+
+        writeln!(env.stdout, "{}", "pruning".green().bold())?;
+
+        let dirs = diary.empty_dirs()?;
+
+        for dir in &dirs {
+            writeln!(
+                env.stdout,
+                "  {} `diary:{}`",
+                "removing".green(),
+                dir.display()
+            )?;
+
+            if !self.dry_run {
+                diary.remove_empty_dir(dir)?;
+            }
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        if dirs.is_empty() {
+            writeln!(env.stdout, "  no empty directories found")?;
+        } else if self.dry_run {
+            writeln!(
+                env.stdout,
+                "  would remove {} empty director{}",
+                dirs.len(),
+                if dirs.len() > 1 { "ies" } else { "y" }
+            )?;
+        } else {
+            writeln!(
+                env.stdout,
+                "  removed {} empty director{}",
+                dirs.len(),
+                if dirs.len() > 1 { "ies" } else { "y" }
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use crate::utils::{
+    get_media_label, get_media_name, parse_media_name, read_media_datetime, DateFilter, DiaryArgs,
+    FileKind, FoundDiaryFile, NameTemplate,
+};
+use crate::Env;
+use anyhow::Result;
+use chrono::NaiveTime;
+use clap::Parser;
+use colored::Colorize;
+use itertools::Itertools;
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, Parser)]
+pub struct RenameCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(flatten)]
+    filter: DateFilter,
+
+    #[clap(long)]
+    name_template: Option<NameTemplate>,
+
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl RenameCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let mut diary = self.diary.open(&env.config)?;
+
+        let template = self
+            .name_template
+            .as_ref()
+            .or(env.config.name_template.as_ref())
+            .cloned();
+
+        let screenshot_prefixes = env.config.screenshot_prefixes.clone().unwrap_or_default();
+        let screencast_prefixes = env.config.screencast_prefixes.clone().unwrap_or_default();
+
+        if self.dry_run {
+            writeln!(env.stdout, "{} is active", "--dry-run".yellow())?;
+            writeln!(env.stdout)?;
+        }
+
+        writeln!(env.stdout, "{}", "renaming".green().bold())?;
+
+        let mut files: Vec<_> = diary
+            .iter()?
+            .filter_map_ok(|file| match file {
+                FoundDiaryFile::Recognized(id) => Some(id),
+                FoundDiaryFile::Unrecognized(_) => None,
+            })
+            .filter_ok(|id| {
+                matches!(
+                    id.kind(),
+                    Some(FileKind::Photo | FileKind::Video | FileKind::Audio)
+                )
+            })
+            .filter_ok(|id| self.filter.matches(id.date))
+            .collect::<Result<_>>()?;
+
+        files.sort_by(|a, b| (a.date, &a.name).cmp(&(b.date, &b.name)));
+
+        let mut renamed = 0;
+        let mut skipped = 0;
+
+        for files in files.chunk_by(|a, b| a.date == b.date) {
+            let mut planned = HashSet::new();
+
+            for src in files {
+                let name = Path::new(&src.name);
+                let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+                let ext = name.extension().unwrap_or_default().to_string_lossy();
+                let kind = src.kind().unwrap();
+
+                let (name_time, name_id) = match parse_media_name(&stem)? {
+                    Some((dt, id)) => (Some(dt.time()), id),
+                    None => match parse_diary_name(&stem) {
+                        Some((time, id)) => (Some(time), Some(id.to_owned())),
+                        None => (None, None),
+                    },
+                };
+
+                let time = read_media_datetime(&diary.file(src), kind)?.map(|dt| dt.time());
+
+                let Some(time) = time.or(name_time) else {
+                    writeln!(
+                        env.stdout,
+                        "  {} `{}` (unknown time)",
+                        "skipping".yellow(),
+                        src
+                    )?;
+
+                    skipped += 1;
+                    continue;
+                };
+
+                // File stays in its day - moving it somewhere else (if the
+                // metadata disagrees) is a job for `diary move`
+                let dt = src.date.and_time(time);
+
+                let id = name_id.or_else(|| stem.strip_prefix("IMG_").map(|id| id.to_owned()));
+
+                let label = get_media_label(&stem, &screenshot_prefixes, &screencast_prefixes);
+                let name = get_media_name(&stem, dt, id.as_deref(), label, template.as_ref());
+
+                // Files kept in subdirectories (e.g. bursts) stay there
+                let name = match Path::new(&src.name).parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => {
+                        format!("{}/{}", dir.display(), name)
+                    }
+                    _ => name,
+                };
+
+                let dst = diary.file_id(src.date, format!("{}.{}", name, ext));
+
+                if dst.name == src.name {
+                    continue;
+                }
+
+                if planned.contains(&dst.name) || diary.has(&dst)? {
+                    writeln!(
+                        env.stdout,
+                        "  {} `{}` (would overwrite `{}`)",
+                        "skipping".yellow(),
+                        src,
+                        dst
+                    )?;
+
+                    skipped += 1;
+                    continue;
+                }
+
+                writeln!(
+                    env.stdout,
+                    "  {} `{}` to `{}`",
+                    "renaming".green(),
+                    src,
+                    dst
+                )?;
+
+                if !self.dry_run {
+                    diary.rename(src, &dst)?;
+
+                    let src_thumb = diary.thumbnail_id(src);
+
+                    if diary.has(&src_thumb)? {
+                        diary.rename(&src_thumb, &diary.thumbnail_id(&dst))?;
+                    }
+
+                    let src_tags = diary.tags_id(src);
+
+                    if diary.has(&src_tags)? {
+                        diary.rename(&src_tags, &diary.tags_id(&dst))?;
+                    }
+                }
+
+                planned.insert(dst.name);
+                renamed += 1;
+            }
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        if renamed == 0 && skipped == 0 {
+            writeln!(env.stdout, "  all names are up to date")?;
+        }
+
+        if renamed > 0 {
+            writeln!(
+                env.stdout,
+                "  {} {} file{}",
+                if self.dry_run {
+                    "would rename"
+                } else {
+                    "renamed"
+                },
+                renamed,
+                if renamed > 1 { "s" } else { "" }
+            )?;
+        }
+
+        if skipped > 0 {
+            writeln!(
+                env.stdout,
+                "  skipped {} file{}",
+                skipped,
+                if skipped > 1 { "s" } else { "" }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses names that already follow the diary's convention, e.g.
+/// `12-34-56 DSC0001`.
+fn parse_diary_name(stem: &str) -> Option<(NaiveTime, &str)> {
+    let (time, id) = stem.split_once(' ')?;
+    let time = NaiveTime::parse_from_str(time, "%H-%M-%S").ok()?;
+
+    Some((time, id))
+}
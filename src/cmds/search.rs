@@ -0,0 +1,95 @@
+use crate::utils::{DateFilter, DiaryArgs, FileKind, FoundDiaryFile};
+use crate::Env;
+use anyhow::{Context, Result};
+use clap::Parser;
+use colored::Colorize;
+use itertools::Itertools;
+use regex::{Regex, RegexBuilder};
+
+#[derive(Debug, Parser)]
+pub struct SearchCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    query: String,
+
+    #[clap(long)]
+    regex: bool,
+
+    #[clap(flatten)]
+    filter: DateFilter,
+}
+
+impl SearchCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let diary = self.diary.open(&env.config)?;
+        let query = self.query()?;
+
+        let mut notes: Vec<_> = diary
+            .iter()?
+            .filter_map_ok(|file| match file {
+                FoundDiaryFile::Recognized(id) => Some(id),
+                FoundDiaryFile::Unrecognized(_) => None,
+            })
+            .filter_ok(|id| id.kind() == Some(FileKind::Note))
+            .filter_ok(|id| self.filter.matches(id.date))
+            .collect::<Result<_>>()?;
+
+        notes.sort_by(|a, b| (a.date, &a.name).cmp(&(b.date, &b.name)));
+
+        for note in notes {
+            let contents = diary.read(&note)?;
+
+            let lines: Vec<_> = contents
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| query.is_match(line))
+                .collect();
+
+            if lines.is_empty() {
+                continue;
+            }
+
+            writeln!(env.stdout, "{}", note.to_string().green().bold())?;
+
+            for (line_idx, line) in lines {
+                writeln!(
+                    env.stdout,
+                    "  {}: {}",
+                    (line_idx + 1).to_string().yellow(),
+                    Self::highlight(&query, line),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn query(&self) -> Result<Regex> {
+        let query = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+
+        RegexBuilder::new(&query)
+            .case_insensitive(true)
+            .build()
+            .with_context(|| format!("invalid query: {}", self.query))
+    }
+
+    fn highlight(query: &Regex, line: &str) -> String {
+        let mut out = String::new();
+        let mut last = 0;
+
+        for m in query.find_iter(line) {
+            out.push_str(&line[last..m.start()]);
+            out.push_str(&m.as_str().red().bold().to_string());
+            last = m.end();
+        }
+
+        out.push_str(&line[last..]);
+        out
+    }
+}
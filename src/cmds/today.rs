@@ -0,0 +1,58 @@
+use crate::utils::{parse_date, DiaryArgs};
+use crate::Env;
+use anyhow::{anyhow, Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::Parser;
+use std::env;
+use std::process::Command;
+
+#[derive(Debug, Parser)]
+pub struct TodayCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    #[clap(value_parser = parse_date, allow_hyphen_values = true)]
+    on: Option<NaiveDate>,
+
+    #[clap(long)]
+    #[clap(default_value = "#+TITLE: {date}")]
+    note_header: String,
+
+    #[clap(long)]
+    edit: bool,
+}
+
+impl TodayCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let mut diary = self.diary.open(&env.config)?;
+        let date = self.on.unwrap_or_else(|| Local::now().date_naive());
+        let note = diary.file_id(date, "index.org");
+
+        if !diary.has(&note)? {
+            #[allow(clippy::literal_string_with_formatting_args)]
+            let header = self.note_header.replace("{date}", &date.to_string());
+
+            diary.create(&note, format!("{}\n", header).as_bytes())?;
+        }
+
+        let path = diary.file(&note);
+
+        if self.edit {
+            let editor = env::var_os("EDITOR").context("missing editor - set `EDITOR`")?;
+
+            let status = Command::new(&editor)
+                .arg(&path)
+                .status()
+                .with_context(|| format!("couldn't launch: {}", editor.to_string_lossy()))?;
+
+            if !status.success() {
+                return Err(anyhow!("{} failed ({})", editor.to_string_lossy(), status));
+            }
+        } else {
+            writeln!(env.stdout, "{}", path.display())?;
+        }
+
+        Ok(())
+    }
+}
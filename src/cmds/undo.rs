@@ -0,0 +1,107 @@
+use crate::utils::{DiaryArgs, JournalAction};
+use crate::Env;
+use anyhow::Result;
+use clap::Parser;
+use colored::Colorize;
+
+#[derive(Debug, Parser)]
+pub struct UndoCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl UndoCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let mut diary = self.diary.open(&env.config)?;
+
+        if self.dry_run {
+            writeln!(env.stdout, "{} is active", "--dry-run".yellow())?;
+            writeln!(env.stdout)?;
+        }
+
+        writeln!(env.stdout, "{}", "undoing".green().bold())?;
+
+        let Some(entry) = diary.journal()?.pop() else {
+            writeln!(env.stdout)?;
+            writeln!(env.stdout, "{}", "summary".green().bold())?;
+            writeln!(env.stdout, "  nothing to undo")?;
+
+            return Ok(());
+        };
+
+        let mut undone = 0;
+
+        // Go backwards, so that e.g. a note's tags get removed before the note
+        // itself
+        for action in entry.actions.iter().rev() {
+            match action {
+                JournalAction::Added { dst } => {
+                    writeln!(
+                        env.stdout,
+                        "  {} `diary:{}`",
+                        "removing".green(),
+                        dst.display()
+                    )?;
+
+                    if !self.dry_run && !diary.remove_rel(dst)? {
+                        writeln!(env.stderr, "    {} file is already gone", "warn".yellow())?;
+                    }
+                }
+
+                JournalAction::Moved { src, dst: rel_path }
+                | JournalAction::Trashed {
+                    src,
+                    trash: rel_path,
+                } => {
+                    writeln!(
+                        env.stdout,
+                        "  {} `{}` from `diary:{}`",
+                        "restoring".green(),
+                        src.display(),
+                        rel_path.display(),
+                    )?;
+
+                    if !self.dry_run && !diary.move_out(rel_path, src)? {
+                        writeln!(env.stderr, "    {} file is already gone", "warn".yellow())?;
+                    }
+                }
+
+                JournalAction::Modified { dst, backup } => {
+                    writeln!(
+                        env.stdout,
+                        "  {} `diary:{}` from `diary:{}`",
+                        "restoring".green(),
+                        dst.display(),
+                        backup.display(),
+                    )?;
+
+                    if !self.dry_run && !diary.restore(backup, dst)? {
+                        writeln!(env.stderr, "    {} backup is already gone", "warn".yellow())?;
+                    }
+                }
+            }
+
+            undone += 1;
+        }
+
+        if !self.dry_run {
+            diary.pop_journal()?;
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        writeln!(
+            env.stdout,
+            "  {} {} change{}",
+            if self.dry_run { "would undo" } else { "undone" },
+            undone,
+            if undone > 1 { "s" } else { "" },
+        )?;
+
+        Ok(())
+    }
+}
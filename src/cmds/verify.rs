@@ -0,0 +1,101 @@
+use crate::utils::{DiaryArgs, FoundDiaryFile, ManifestFormat};
+use crate::Env;
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+pub struct VerifyCmd {
+    #[clap(flatten)]
+    diary: DiaryArgs,
+
+    #[clap(long)]
+    manifest: PathBuf,
+
+    #[clap(long)]
+    #[clap(value_enum, default_value_t = ManifestFormat::Blake3)]
+    manifest_format: ManifestFormat,
+}
+
+impl VerifyCmd {
+    pub fn run(self, env: &mut Env) -> Result<()> {
+        let diary = self.diary.open(&env.config)?;
+        let mut expected = self.load_manifest()?;
+        let mut problems = Vec::new();
+
+        writeln!(env.stdout, "{}", "verifying".green().bold())?;
+
+        for file in diary.iter()? {
+            let FoundDiaryFile::Recognized(id) = file? else {
+                continue;
+            };
+
+            // Tag sidecars get rewritten by `add --tag`, so they don't make it
+            // into the manifest
+            if id.name.ends_with(".tags") {
+                continue;
+            }
+
+            let Some(expected) = expected.remove(&diary.rel_file(&id).display().to_string()) else {
+                problems.push(format!("`{}`: not in the manifest", id));
+                continue;
+            };
+
+            let actual = self.manifest_format.hash_file(diary.file(&id))?;
+
+            if actual != expected {
+                problems.push(format!("`{}`: content has changed", id));
+            }
+        }
+
+        for path in expected.keys() {
+            problems.push(format!("`diary:{}`: missing", path));
+        }
+
+        for problem in &problems {
+            writeln!(env.stdout, "  {} {}", "problem".yellow(), problem)?;
+        }
+
+        writeln!(env.stdout)?;
+        writeln!(env.stdout, "{}", "summary".green().bold())?;
+
+        if problems.is_empty() {
+            writeln!(env.stdout, "  no problems found")?;
+
+            Ok(())
+        } else {
+            writeln!(env.stdout, "  {} problem(s) found", problems.len())?;
+
+            Err(anyhow!("diary has {} problem(s)", problems.len()))
+        }
+    }
+
+    /// Loads manifest written by `add --hash-manifest`, returning a map of
+    /// file (relative to the diary's root) -> hash.
+    fn load_manifest(&self) -> Result<BTreeMap<String, String>> {
+        let manifest = fs::read_to_string(&self.manifest)
+            .with_context(|| format!("couldn't read: {}", self.manifest.display()))?;
+
+        manifest
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (hash, path) = line
+                    .split_once("  ")
+                    .with_context(|| format!("invalid manifest entry: {}", line))?;
+
+                if !self.manifest_format.is_hash(hash) {
+                    return Err(anyhow!(
+                        "invalid manifest entry (is `--manifest-format` right?): {}",
+                        line
+                    ));
+                }
+
+                Ok((path.to_owned(), hash.to_owned()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,86 @@
+use crate::utils::{Layout, NameTemplate};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Defaults loaded from `diary.toml`.
+///
+/// Values given here are used only when they're not provided through the
+/// command line or the environment.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diary: Option<PathBuf>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes_subdir: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_subdir: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_template: Option<NameTemplate>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_prefixes: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screencast_prefixes: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ext_aliases: Option<BTreeMap<String, String>>,
+}
+
+impl Config {
+    pub const FILE_NAME: &'static str = "diary.toml";
+
+    /// Loads the config from the current directory or, if it's not present
+    /// there, from `$XDG_CONFIG_HOME/diary/`.
+    pub fn load() -> Result<Self> {
+        for path in Self::paths() {
+            if path.try_exists()? {
+                return Self::load_from(&path);
+            }
+        }
+
+        Ok(Self::default())
+    }
+
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let config: Result<_> = try {
+            let config = fs::read_to_string(path)?;
+
+            toml::from_str(&config)?
+        };
+
+        config.with_context(|| format!("couldn't load config: {}", path.display()))
+    }
+
+    fn paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(Self::FILE_NAME)];
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+
+        if let Some(config_dir) = config_dir {
+            paths.push(config_dir.join("diary").join(Self::FILE_NAME));
+        }
+
+        paths
+    }
+}
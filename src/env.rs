@@ -1,5 +1,55 @@
-use std::io::Write;
+use crate::Config;
+use std::io::{self, Write};
 
 pub struct Env<'a> {
     pub stdout: &'a mut dyn Write,
+
+    /// Where warnings go, so that they don't get mixed with the actual output
+    /// (e.g. `--format json`)
+    pub stderr: &'a mut dyn Write,
+
+    pub config: Config,
+    pub verbosity: Verbosity,
+
+    /// Whether stdout is connected to a terminal (as opposed to a file or a
+    /// pipe), in which case we can display progress bars etc.
+    pub is_terminal: bool,
+}
+
+impl Env<'_> {
+    /// Runs given function with stdout discarded; warnings still get through,
+    /// unless the verbosity is [`Verbosity::Silent`].
+    pub fn muted<T>(&mut self, f: impl FnOnce(&mut Env) -> T) -> T {
+        let mut sink = io::sink();
+        let mut stderr_sink = io::sink();
+
+        let stderr: &mut dyn Write = if self.verbosity == Verbosity::Silent {
+            &mut stderr_sink
+        } else {
+            self.stderr
+        };
+
+        let mut env = Env {
+            stdout: &mut sink,
+            stderr,
+            config: self.config.clone(),
+            verbosity: Verbosity::Silent,
+            is_terminal: false,
+        };
+
+        f(&mut env)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Prints nothing.
+    Silent,
+
+    /// Prints only the summary.
+    Quiet,
+
+    /// Prints everything.
+    #[default]
+    Normal,
 }
@@ -1,23 +1,61 @@
 #![feature(try_blocks)]
 
 mod cmds;
+mod config;
 mod env;
 mod utils;
 
 pub use self::cmds::*;
+pub use self::config::*;
 pub use self::env::*;
 use anyhow::Result;
 use clap::Parser;
 
 #[derive(Debug, Parser)]
+#[allow(clippy::large_enum_variant)]
 pub enum Cmd {
     Add(AddCmd),
+    Check(CheckCmd),
+    Dedup(DedupCmd),
+    Export(ExportCmd),
+    Init(InitCmd),
+    List(ListCmd),
+    #[clap(alias = "relocate")]
+    Move(MoveCmd),
+    Prune(PruneCmd),
+    Rename(RenameCmd),
+    Search(SearchCmd),
+    #[clap(alias = "open")]
+    Today(TodayCmd),
+    Undo(UndoCmd),
+    Verify(VerifyCmd),
 }
 
 impl Cmd {
+    /// Loads the config this command runs with; `init` doesn't read it, so
+    /// it's not bothered by a broken one.
+    pub fn config(&self) -> Result<Config> {
+        match self {
+            Cmd::Init(_) => Ok(Config::default()),
+            _ => Config::load(),
+        }
+    }
+
     pub fn run(self, env: &mut Env) -> Result<()> {
         match self {
             Cmd::Add(cmd) => cmd.run(env),
+            Cmd::Check(cmd) => cmd.run(env),
+            Cmd::Dedup(cmd) => cmd.run(env),
+            Cmd::Export(cmd) => cmd.run(env),
+            Cmd::Init(cmd) => cmd.run(env),
+            Cmd::List(cmd) => cmd.run(env),
+            Cmd::Move(cmd) => cmd.run(env),
+            Cmd::Prune(cmd) => cmd.run(env),
+            Cmd::Rename(cmd) => cmd.run(env),
+            Cmd::Search(cmd) => cmd.run(env),
+            Cmd::Today(cmd) => cmd.run(env),
+            Cmd::Undo(cmd) => cmd.run(env),
+            Cmd::Verify(cmd) => cmd.run(env),
         }
     }
 }
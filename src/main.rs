@@ -1,14 +1,34 @@
 use anyhow::Result;
 use clap::Parser;
-use diary::{Cmd, Env};
-use std::io;
+use diary::{Cmd, Env, Verbosity};
+use std::io::{self, IsTerminal};
+use tracing_subscriber::EnvFilter;
 
 fn main() -> Result<()> {
+    // Parsed before loading the config, so that a broken config doesn't get
+    // in the way of `--help`
+    let cmd = Cmd::parse();
+
+    // Diagnostics go to stderr, so that they don't get mixed with the actual
+    // output (e.g. `--format json`)
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+
     let mut stdout = io::stdout().lock();
 
+    // (not locked, since tracing writes into stderr from worker threads, too)
+    let mut stderr = io::stderr();
+
     let mut env = Env {
         stdout: &mut stdout,
+        stderr: &mut stderr,
+        config: cmd.config()?,
+        verbosity: Verbosity::default(),
+        is_terminal: io::stdout().is_terminal(),
     };
 
-    Cmd::parse().run(&mut env)
+    cmd.run(&mut env)
 }
@@ -1,5 +1,39 @@
+mod animation;
+mod convert;
+mod date_filter;
+mod diary_args;
+mod diary_backend;
 mod diary_repository;
+mod ext_alias;
+mod file_kind;
+mod gps;
+mod hash;
+mod journal;
+mod layout;
+mod media_name;
+mod name_template;
+mod note_links;
+mod size;
 mod source_repository;
+mod tags;
+mod time_shift;
 
+pub use self::animation::*;
+pub use self::convert::*;
+pub use self::date_filter::*;
+pub use self::diary_args::*;
+pub use self::diary_backend::*;
 pub use self::diary_repository::*;
+pub use self::ext_alias::*;
+pub use self::file_kind::*;
+pub use self::gps::*;
+pub use self::hash::*;
+pub use self::journal::*;
+pub use self::layout::*;
+pub use self::media_name::*;
+pub use self::name_template::*;
+pub use self::note_links::*;
+pub use self::size::*;
 pub use self::source_repository::*;
+pub use self::tags::*;
+pub use self::time_shift::*;
@@ -0,0 +1,181 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a WebP / AVIF file is needed to tell whether it's animated.
+const HEADER_LEN: u64 = 4096;
+
+/// Returns whether given image (judging by its extension, a `gif`, `webp` or
+/// `avif` one) has more than one frame; unreadable or malformed files are
+/// considered static.
+pub fn is_animated(path: &Path, ext: &str) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+
+    // GIFs don't announce animation upfront, so frames have to be counted
+    let len = if ext == "gif" { u64::MAX } else { HEADER_LEN };
+    let mut data = Vec::new();
+
+    if file.take(len).read_to_end(&mut data).is_err() {
+        return false;
+    }
+
+    match ext {
+        "gif" => is_animated_gif(&data).unwrap_or(false),
+        "webp" => is_animated_webp(&data),
+        "avif" => is_animated_avif(&data),
+        _ => false,
+    }
+}
+
+fn is_animated_gif(data: &[u8]) -> Option<bool> {
+    if !data.starts_with(b"GIF8") {
+        return Some(false);
+    }
+
+    let mut pos = 13 + color_table_len(*data.get(10)?);
+    let mut frames = 0;
+
+    loop {
+        match *data.get(pos)? {
+            // Extension (label + sub-blocks)
+            0x21 => {
+                pos = skip_sub_blocks(data, pos + 2)?;
+            }
+
+            // Image (descriptor + optional color table + LZW code size +
+            // sub-blocks)
+            0x2c => {
+                frames += 1;
+
+                if frames > 1 {
+                    return Some(true);
+                }
+
+                pos += 10 + color_table_len(*data.get(pos + 9)?);
+                pos = skip_sub_blocks(data, pos + 1)?;
+            }
+
+            // Trailer
+            _ => return Some(false),
+        }
+    }
+}
+
+fn color_table_len(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+fn skip_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+
+        pos += 1 + len;
+
+        if len == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+/// Animated WebPs carry the extended `VP8X` chunk with the animation flag set.
+fn is_animated_webp(data: &[u8]) -> bool {
+    data.get(0..4) == Some(b"RIFF")
+        && data.get(8..12) == Some(b"WEBP")
+        && data.get(12..16) == Some(b"VP8X")
+        && data.get(20).is_some_and(|flags| flags & 0x02 != 0)
+}
+
+/// Animated AVIFs (image sequences) list the `avis` brand in their `ftyp` box.
+fn is_animated_avif(data: &[u8]) -> bool {
+    if data.get(4..8) != Some(b"ftyp") {
+        return false;
+    }
+
+    let Some(len) = data.get(0..4) else {
+        return false;
+    };
+
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    let brands = data.get(8..len.min(data.len())).unwrap_or_default();
+
+    // (major brand, minor version, compatible brands)
+    brands
+        .chunks_exact(4)
+        .enumerate()
+        .any(|(idx, brand)| idx != 1 && brand == b"avis")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    /// 1x1 GIF with given number of frames.
+    fn gif(frames: usize) -> Vec<u8> {
+        let mut data = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xff\xff\xff".to_vec();
+
+        // NETSCAPE2.0 looping extension
+        data.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        for _ in 0..frames {
+            data.extend(b"\x21\xf9\x04\x00\x0a\x00\x00\x00");
+            data.extend(b"\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00");
+        }
+
+        data.push(0x3b);
+        data
+    }
+
+    fn webp(chunk: &[u8], flags: u8) -> Vec<u8> {
+        let mut data = b"RIFF\x00\x00\x00\x00WEBP".to_vec();
+
+        data.extend(chunk);
+        data.extend([0x0a, 0x00, 0x00, 0x00, flags, 0x00, 0x00, 0x00]);
+        data
+    }
+
+    fn avif(brands: &[&[u8; 4]]) -> Vec<u8> {
+        let mut data = ((8 + 4 * (brands.len() + 1)) as u32).to_be_bytes().to_vec();
+
+        data.extend(b"ftyp");
+
+        for (idx, brand) in brands.iter().enumerate() {
+            data.extend(*brand);
+
+            // (minor version)
+            if idx == 0 {
+                data.extend([0; 4]);
+            }
+        }
+
+        data
+    }
+
+    #[test_case(gif(1), false ; "gif static")]
+    #[test_case(gif(2), true ; "gif animated")]
+    #[test_case(gif(2)[..40].to_vec(), false ; "gif truncated")]
+    fn gif_frames(data: Vec<u8>, expected: bool) {
+        assert_eq!(expected, is_animated_gif(&data).unwrap_or(false));
+    }
+
+    #[test_case(webp(b"VP8 ", 0x02), false ; "webp simple")]
+    #[test_case(webp(b"VP8X", 0x00), false ; "webp extended")]
+    #[test_case(webp(b"VP8X", 0x12), true ; "webp animated")]
+    fn webp_flags(data: Vec<u8>, expected: bool) {
+        assert_eq!(expected, is_animated_webp(&data));
+    }
+
+    #[test_case(avif(&[b"avif", b"mif1", b"miaf"]), false ; "avif static")]
+    #[test_case(avif(&[b"avis", b"avif", b"msf1"]), true ; "avif sequence")]
+    #[test_case(avif(&[b"avif", b"avis"]), true ; "avif compatible sequence")]
+    #[test_case(b"\x00\x00\x00\x0cjP  ".to_vec(), false ; "not avif")]
+    fn avif_brands(data: Vec<u8>, expected: bool) {
+        assert_eq!(expected, is_animated_avif(&data));
+    }
+}
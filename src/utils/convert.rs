@@ -0,0 +1,163 @@
+use crate::utils::FileKind;
+use anyhow::{anyhow, Context, Result};
+use filetime::FileTime;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::process::Command;
+
+/// Transcodes a HEIC image into JPEG, using `heif-convert`.
+pub fn convert_heic_to_jpeg(src: &Path, dst: &Path) -> Result<()> {
+    let status = Command::new("heif-convert")
+        .arg("-q")
+        .arg("90")
+        .arg(src)
+        .arg(dst)
+        .output()
+        .context("couldn't launch heif-convert")?
+        .status;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "heif-convert failed for `{}` ({})",
+            src.display(),
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+/// Removes all metadata (EXIF, GPS etc.) from given file, in place, using
+/// `exiftool`.
+pub fn strip_metadata(path: &Path) -> Result<()> {
+    let status = Command::new("exiftool")
+        .arg("-q")
+        .arg("-all=")
+        .arg("-overwrite_original")
+        .arg(path)
+        .output()
+        .context("couldn't launch exiftool")?
+        .status;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "exiftool failed to strip metadata from `{}` ({})",
+            path.display(),
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+/// Bakes photo's EXIF orientation into its pixels, in place, using
+/// ImageMagick - so that the photo looks right even in viewers that ignore
+/// the orientation flag; the flag itself gets reset.
+///
+/// Returns whether the photo had to be rotated (or flipped).
+pub fn auto_orient(path: &Path) -> Result<bool> {
+    if read_orientation(path).unwrap_or(1) == 1 {
+        return Ok(false);
+    }
+
+    let mtime = FileTime::from_last_modification_time(
+        &fs::metadata(path)
+            .with_context(|| format!("couldn't read metadata: {}", path.display()))?,
+    );
+
+    let status = Command::new("magick")
+        .arg(path)
+        .arg("-auto-orient")
+        .arg(path)
+        .output()
+        .context("couldn't launch magick")?
+        .status;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "magick failed to orient `{}` ({})",
+            path.display(),
+            status
+        ));
+    }
+
+    // Re-encoding shouldn't make the photo look like it's been just taken
+    filetime::set_file_mtime(path, mtime)
+        .with_context(|| format!("couldn't set modification time: {}", path.display()))?;
+
+    Ok(true)
+}
+
+/// Reads photo's EXIF orientation (`1` meaning it's stored upright), if it
+/// has one.
+fn read_orientation(path: &Path) -> Option<u32> {
+    let file = File::open(path).ok()?;
+
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+/// Generates a JPEG thumbnail of given photo or video, using ImageMagick or
+/// ffmpeg, respectively; `size` is the longest edge, in pixels.
+pub fn generate_thumbnail(path: &Path, kind: FileKind, size: u32) -> Result<Vec<u8>> {
+    let mut cmd = match kind {
+        FileKind::Photo => {
+            let mut cmd = Command::new("magick");
+
+            cmd.arg(path)
+                .arg("-auto-orient")
+                .arg("-thumbnail")
+                .arg(format!("{}x{}>", size, size))
+                .arg("jpg:-");
+
+            cmd
+        }
+
+        FileKind::Video => {
+            let mut cmd = Command::new("ffmpeg");
+
+            cmd.arg("-v")
+                .arg("error")
+                .arg("-i")
+                .arg(path)
+                .arg("-frames:v")
+                .arg("1")
+                .arg("-vf")
+                .arg(format!(
+                    "scale={}:{}:force_original_aspect_ratio=decrease",
+                    size, size
+                ))
+                .arg("-f")
+                .arg("image2")
+                .arg("-c:v")
+                .arg("mjpeg")
+                .arg("-");
+
+            cmd
+        }
+
+        _ => {
+            return Err(anyhow!("cannot generate thumbnail for: {}", path.display()));
+        }
+    };
+
+    let out = cmd
+        .output()
+        .with_context(|| format!("couldn't launch {:?}", cmd.get_program()))?;
+
+    if !out.status.success() || out.stdout.is_empty() {
+        return Err(anyhow!(
+            "couldn't generate thumbnail for `{}` ({})",
+            path.display(),
+            out.status
+        ));
+    }
+
+    Ok(out.stdout)
+}
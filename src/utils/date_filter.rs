@@ -0,0 +1,177 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Weekday};
+use clap::Args;
+
+#[derive(Debug, Args)]
+pub struct DateFilter {
+    #[clap(long)]
+    #[clap(value_parser = parse_date, allow_hyphen_values = true)]
+    on: Option<NaiveDate>,
+
+    #[clap(long)]
+    #[clap(value_parser = parse_date, allow_hyphen_values = true)]
+    #[clap(conflicts_with = "on")]
+    from: Option<NaiveDate>,
+
+    #[clap(long)]
+    #[clap(value_parser = parse_date, allow_hyphen_values = true)]
+    #[clap(conflicts_with = "on")]
+    #[clap(requires = "from")]
+    to: Option<NaiveDate>,
+
+    #[clap(long)]
+    #[clap(conflicts_with_all = ["on", "from", "to"])]
+    year: Option<i32>,
+
+    #[clap(long)]
+    #[clap(value_parser = parse_month)]
+    #[clap(conflicts_with_all = ["on", "from", "to", "year"])]
+    month: Option<NaiveDate>,
+
+    #[clap(long = "weekday")]
+    #[clap(value_parser = parse_weekday)]
+    weekdays: Vec<Weekday>,
+}
+
+impl DateFilter {
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        let (from, to) = self.range();
+
+        let on = self.on.is_none_or(|on| date == on);
+        let from = from.is_none_or(|from| date >= from);
+        let to = to.is_none_or(|to| date <= to);
+        let weekday = self.weekdays.is_empty() || self.weekdays.contains(&date.weekday());
+
+        on && from && to && weekday
+    }
+
+    fn range(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        if let Some(year) = self.year {
+            return (
+                NaiveDate::from_ymd_opt(year, 1, 1),
+                NaiveDate::from_ymd_opt(year, 12, 31),
+            );
+        }
+
+        if let Some(month) = self.month {
+            return (
+                Some(month),
+                month
+                    .checked_add_months(Months::new(1))
+                    .and_then(|date| date.pred_opt()),
+            );
+        }
+
+        (self.from, self.to)
+    }
+}
+
+/// Parses a date given either as `YYYY-MM-DD` or relative to today - as
+/// `today`, `yesterday` or an offset such as `-7d`, `-2w`, `-1m` or `-1y`.
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    resolve_date(s, Local::now().date_naive())
+}
+
+fn resolve_date(s: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let err = || {
+        format!(
+            "invalid date (expected YYYY-MM-DD, today, yesterday or an offset like -7d): {}",
+            s
+        )
+    };
+
+    match s {
+        "today" => return Ok(today),
+        "yesterday" => return today.pred_opt().ok_or_else(err),
+        _ => (),
+    }
+
+    let Some(offset) = s.strip_prefix(['-', '+']) else {
+        return NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| err());
+    };
+
+    let is_past = s.starts_with('-');
+
+    let (n, unit) = offset.split_at(offset.len().saturating_sub(1));
+    let n: u32 = n.parse().map_err(|_| err())?;
+
+    let date = match (unit, is_past) {
+        ("d", true) => today.checked_sub_days(Days::new(n.into())),
+        ("d", false) => today.checked_add_days(Days::new(n.into())),
+        ("w", true) => today.checked_sub_days(Days::new(7 * u64::from(n))),
+        ("w", false) => today.checked_add_days(Days::new(7 * u64::from(n))),
+        ("m", true) => today.checked_sub_months(Months::new(n)),
+        ("m", false) => today.checked_add_months(Months::new(n)),
+        ("y", true) => today.checked_sub_months(Months::new(12 * n)),
+        ("y", false) => today.checked_add_months(Months::new(12 * n)),
+        _ => None,
+    };
+
+    date.ok_or_else(err)
+}
+
+fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
+        .map_err(|_| format!("invalid month (expected YYYY-MM): {}", s))
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday, String> {
+    s.parse()
+        .map_err(|_| format!("invalid weekday (expected mon, tue, ...): {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use test_case::test_case;
+
+    #[derive(Parser)]
+    struct Cmd {
+        #[clap(flatten)]
+        filter: DateFilter,
+    }
+
+    #[test_case("", "2018-01-01", true)]
+    #[test_case("--weekday mon", "2018-01-01", true)]
+    #[test_case("--weekday mon", "2018-01-02", false)]
+    #[test_case("--weekday mon --weekday tue", "2018-01-02", true)]
+    #[test_case("--weekday Wednesday", "2018-01-03", true)]
+    #[test_case("--weekday mon --from 2018-01-02", "2018-01-01", false)]
+    #[test_case("--weekday mon --from 2018-01-02", "2018-01-08", true)]
+    #[test_case("--weekday mon --month 2018-01", "2018-02-05", false)]
+    fn matches(args: &str, date: &str, expected: bool) {
+        let cmd = Cmd::parse_from(["diary"].into_iter().chain(args.split_whitespace()));
+        let date = date.parse().unwrap();
+
+        assert_eq!(expected, cmd.filter.matches(date));
+    }
+
+    #[test_case("2018-01-02", Some("2018-01-02"))]
+    #[test_case("today", Some("2018-03-31"))]
+    #[test_case("yesterday", Some("2018-03-30"))]
+    #[test_case("-7d", Some("2018-03-24"))]
+    #[test_case("+1d", Some("2018-04-01"))]
+    #[test_case("-2w", Some("2018-03-17"))]
+    #[test_case("-1m", Some("2018-02-28") ; "month clamped")]
+    #[test_case("-1y", Some("2017-03-31"))]
+    #[test_case("-d", None ; "missing count")]
+    #[test_case("-7", None ; "missing unit")]
+    #[test_case("-7h", None ; "unknown unit")]
+    #[test_case("tomorrow", None)]
+    fn resolve(given: &str, expected: Option<&str>) {
+        let today = NaiveDate::from_ymd_opt(2018, 3, 31).unwrap();
+        let actual = resolve_date(given, today).ok().map(|date| date.to_string());
+
+        assert_eq!(expected.map(String::from), actual);
+    }
+
+    #[test]
+    fn relative_from() {
+        let cmd = Cmd::parse_from(["diary", "--from", "-1d"]);
+
+        assert_eq!(
+            Some(Local::now().date_naive().pred_opt().unwrap()),
+            cmd.filter.from
+        );
+    }
+}
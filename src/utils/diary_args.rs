@@ -0,0 +1,53 @@
+use crate::utils::{DiaryRepository, Layout};
+use crate::Config;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Debug, Args)]
+pub struct DiaryArgs {
+    #[clap(long)]
+    diary: Option<PathBuf>,
+
+    #[clap(long)]
+    layout: Option<Layout>,
+
+    #[clap(long)]
+    notes_subdir: Option<String>,
+
+    #[clap(long)]
+    media_subdir: Option<String>,
+}
+
+impl DiaryArgs {
+    pub fn open(&self, config: &Config) -> Result<DiaryRepository> {
+        let dir = self
+            .diary
+            .clone()
+            .or_else(|| env::var_os("DIARY_PATH").map(PathBuf::from))
+            .or_else(|| config.diary.clone())
+            .context(
+                "missing diary directory - use `--diary`, set `DIARY_PATH` or set it in the config",
+            )?;
+
+        let layout = self
+            .layout
+            .as_ref()
+            .or(config.layout.as_ref())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut diary = DiaryRepository::new(&dir)?.with_layout(layout);
+
+        if let Some(subdir) = self.notes_subdir.as_ref().or(config.notes_subdir.as_ref()) {
+            diary = diary.with_notes_subdir(subdir);
+        }
+
+        if let Some(subdir) = self.media_subdir.as_ref().or(config.media_subdir.as_ref()) {
+            diary = diary.with_media_subdir(subdir);
+        }
+
+        Ok(diary)
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{fmt, fs, io};
+
+/// Storage the diary's files live in.
+///
+/// All paths are relative to the diary's root.
+pub trait DiaryBackend: fmt::Debug + Send + Sync {
+    /// Returns the diary's directory, if it's stored on the local filesystem.
+    ///
+    /// Operations other than the ones provided by this trait (e.g. reading
+    /// notes or hashing files) are supported only on local diaries.
+    fn local_dir(&self) -> Option<&Path>;
+
+    fn has(&self, path: &Path) -> Result<bool>;
+
+    /// Copies a local file into the diary, overwriting the destination.
+    ///
+    /// `progress` gets called with the number of bytes copied so far (if the
+    /// backend is able to tell).
+    fn add(
+        &self,
+        src: &Path,
+        dst: &Path,
+        buffer_size: usize,
+        progress: &mut dyn FnMut(u64),
+    ) -> Result<()>;
+
+    /// Creates given directory, together with its parents.
+    fn mkdir(&self, dir: &Path) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub struct LocalBackend {
+    dir: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+}
+
+impl DiaryBackend for LocalBackend {
+    fn local_dir(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+
+    fn has(&self, path: &Path) -> Result<bool> {
+        Ok(self.dir.join(path).try_exists()?)
+    }
+
+    fn add(
+        &self,
+        src: &Path,
+        dst: &Path,
+        buffer_size: usize,
+        progress: &mut dyn FnMut(u64),
+    ) -> Result<()> {
+        let dst = self.dir.join(dst);
+
+        copy_file(src, &dst, buffer_size, progress)
+            .with_context(|| format!("couldn't copy `{}` to `{}`", src.display(), dst.display()))
+    }
+
+    fn mkdir(&self, dir: &Path) -> Result<()> {
+        let dir = self.dir.join(dir);
+
+        if !dir.try_exists()? {
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Diary stored on an rclone remote (e.g. `nas:diary`), accessed through the
+/// `rclone` binary.
+#[derive(Debug)]
+pub struct RcloneBackend {
+    remote: String,
+}
+
+impl RcloneBackend {
+    /// Exit code returned by `rclone` when the directory doesn't exist.
+    const DIR_NOT_FOUND: i32 = 3;
+
+    /// Recognizes `remote:path`; single-letter remotes are not supported, so
+    /// that Windows paths such as `C:\diary` remain local.
+    pub fn parse(dir: &Path) -> Option<Self> {
+        let dir = dir.to_str()?;
+        let (name, _) = dir.split_once(':')?;
+
+        let is_remote = name.len() > 1
+            && name
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.' | ' '));
+
+        is_remote.then(|| Self {
+            remote: dir.to_owned(),
+        })
+    }
+
+    fn path(&self, path: &Path) -> String {
+        let path = path.to_string_lossy();
+
+        if path.is_empty() {
+            self.remote.clone()
+        } else if self.remote.ends_with([':', '/']) {
+            format!("{}{}", self.remote, path)
+        } else {
+            format!("{}/{}", self.remote, path)
+        }
+    }
+
+    /// Runs `rclone` with given arguments, returning its exit code and stdout;
+    /// exit codes other than zero are errors, unless listed in `allowed_codes`.
+    fn rclone(&self, args: &[&str], allowed_codes: &[i32]) -> Result<(i32, String)> {
+        let out = Command::new("rclone")
+            .args(args)
+            .output()
+            .context("couldn't launch rclone")?;
+
+        let code = out.status.code().unwrap_or(-1);
+
+        if !out.status.success() && !allowed_codes.contains(&code) {
+            return Err(anyhow!(
+                "rclone {} failed ({}): {}",
+                args.join(" "),
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim(),
+            ));
+        }
+
+        Ok((code, String::from_utf8_lossy(&out.stdout).into_owned()))
+    }
+}
+
+impl DiaryBackend for RcloneBackend {
+    fn local_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    fn has(&self, path: &Path) -> Result<bool> {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Ok(false);
+        };
+
+        let (code, files) = self.rclone(
+            &["lsf", "--files-only", &self.path(dir)],
+            &[Self::DIR_NOT_FOUND],
+        )?;
+
+        if code == Self::DIR_NOT_FOUND {
+            return Ok(false);
+        }
+
+        Ok(files.lines().any(|file| name == file))
+    }
+
+    fn add(
+        &self,
+        src: &Path,
+        dst: &Path,
+        _buffer_size: usize,
+        _progress: &mut dyn FnMut(u64),
+    ) -> Result<()> {
+        let src = src
+            .to_str()
+            .with_context(|| format!("path is not valid unicode: {}", src.display()))?;
+
+        self.rclone(&["copyto", src, &self.path(dst)], &[])?;
+
+        Ok(())
+    }
+
+    fn mkdir(&self, dir: &Path) -> Result<()> {
+        self.rclone(&["mkdir", &self.path(dir)], &[])?;
+
+        Ok(())
+    }
+}
+
+/// Copies file in chunks of given size, reporting progress after each chunk.
+///
+/// (unlike `fs::copy()`, which doesn't tell how far it is - a problem for
+/// multi-gigabyte videos.)
+fn copy_file(
+    src: &Path,
+    dst: &Path,
+    buffer_size: usize,
+    progress: &mut dyn FnMut(u64),
+) -> Result<()> {
+    let mut reader = File::open(src)?;
+    let mut writer = File::create(dst)?;
+    let mut buffer = vec![0; buffer_size.max(1)];
+    let mut copied = 0;
+
+    loop {
+        let len = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        writer.write_all(&buffer[..len])?;
+        copied += len as u64;
+        progress(copied);
+    }
+
+    writer.flush()?;
+
+    fs::set_permissions(dst, reader.metadata()?.permissions())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("nas:diary", Some("nas:diary"))]
+    #[test_case("my-nas:", Some("my-nas:"))]
+    #[test_case("/home/user/diary", None)]
+    #[test_case("./weird:name", None)]
+    #[test_case("C:\\diary", None)]
+    fn parse(dir: &str, expected: Option<&str>) {
+        let actual = RcloneBackend::parse(Path::new(dir));
+
+        assert_eq!(
+            expected,
+            actual.as_ref().map(|backend| backend.remote.as_str())
+        );
+    }
+
+    #[test_case("nas:diary", "2018/01/02", "nas:diary/2018/01/02")]
+    #[test_case("nas:diary/", "2018/01/02", "nas:diary/2018/01/02" ; "trailing slash")]
+    #[test_case("nas:", "2018/01/02", "nas:2018/01/02")]
+    #[test_case("nas:diary", "", "nas:diary")]
+    fn path(remote: &str, path: &str, expected: &str) {
+        let backend = RcloneBackend::parse(Path::new(remote)).unwrap();
+
+        assert_eq!(expected, backend.path(Path::new(path)));
+    }
+}
@@ -1,95 +1,1044 @@
+use crate::utils::{
+    convert_heic_to_jpeg, hash_file, move_file, DiaryBackend, FileKind, JournalEntry, Layout,
+    LocalBackend, RcloneBackend,
+};
+use crate::Config;
 use anyhow::{anyhow, Context, Result};
-use chrono::{Datelike, NaiveDate};
+use chrono::{DateTime, Local, NaiveDate};
+use clap::ValueEnum;
+use filetime::FileTime;
+use fs2::FileExt;
+use glob::glob;
+use itertools::Itertools;
+use serde::{Serialize, Serializer};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::{fmt, fs};
+use std::{fmt, fs, io};
+
+#[cfg(unix)]
+use std::os::unix::fs::{symlink, symlink as symlink_dir};
+
+#[cfg(windows)]
+use std::os::windows::fs::{symlink_dir, symlink_file as symlink};
+
+/// Name of the per-day directory containing thumbnails (see `--thumbnails`).
+const THUMBS_DIR: &str = "thumbs";
+
+/// Name of the per-day directory grouping burst-mode sequences, each in its
+/// own subdirectory (see `add --no-group-bursts`).
+pub const BURSTS_DIR: &str = "burst";
+
+/// Prefix of per-day directories grouping media into events, e.g. `event-1`
+/// (see `add --events`).
+pub const EVENT_DIR_PREFIX: &str = "event-";
+
+/// Path of the file storing timestamp of the last `add --since-last-run`.
+const LAST_RUN_FILE: &str = ".diary/last-run";
+
+/// Path of the file listing imports which can be reverted (see `add
+/// --journal` and `diary undo`), one JSON entry per line.
+const JOURNAL_FILE: &str = ".diary/journal.jsonl";
+
+/// Path of the directory keeping source files removed by `add --journal`, so
+/// that `diary undo` can restore them.
+const TRASH_DIR: &str = ".diary/trash";
+
+/// Path of the file locked while an import is writing into the diary, so that
+/// two imports don't race each other.
+const LOCK_FILE: &str = ".diary/lock";
+
+/// Name of the file created (and removed right away) to check whether a
+/// directory is writable (see `add --check-writable`).
+const WRITE_CHECK_FILE: &str = ".diary-write-check";
+
+/// Name of the symlink pointing at the newest day (see `add --link-latest`).
+const LATEST_LINK: &str = "latest";
+
+/// Default size of the buffer used to copy files into the diary - large
+/// enough for the copy to keep up with `fs::copy()`.
+const DEFAULT_COPY_BUFFER_SIZE: usize = 1024 * 1024;
 
 #[derive(Debug)]
 pub struct DiaryRepository {
     dir: PathBuf,
+    backend: Box<dyn DiaryBackend>,
+    layout: Layout,
+    notes_subdir: Option<String>,
+    media_subdir: Option<String>,
+    preserve_time: bool,
+    on_conflict: OnConflict,
+    copy_buffer_size: usize,
 }
 
 impl DiaryRepository {
+    /// Opens diary at given directory or, if it looks like `remote:path`, at
+    /// given rclone remote.
     pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
         let dir = dir.as_ref();
 
-        if !dir.try_exists()? {
-            return Err(anyhow!("diary directory not found: {}", dir.display()));
-        }
+        let backend: Box<dyn DiaryBackend> = if let Some(backend) = RcloneBackend::parse(dir) {
+            Box::new(backend)
+        } else {
+            if !dir.try_exists()? {
+                return Err(anyhow!("diary directory not found: {}", dir.display()));
+            }
+
+            Box::new(LocalBackend::new(dir))
+        };
 
         Ok(Self {
             dir: dir.to_owned(),
+            backend,
+            layout: Layout::default(),
+            notes_subdir: None,
+            media_subdir: None,
+            preserve_time: true,
+            on_conflict: OnConflict::default(),
+            copy_buffer_size: DEFAULT_COPY_BUFFER_SIZE,
         })
     }
 
-    pub fn dir(&self, date: NaiveDate) -> PathBuf {
-        self.dir
-            .join(format!("{:04}", date.year()))
-            .join(format!("{:02}", date.month()))
-            .join(format!("{:02}", date.day()))
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Puts notes into given subdirectory of the diary, e.g. `journal`, so
+    /// that they live apart from media.
+    pub fn with_notes_subdir(mut self, subdir: impl AsRef<str>) -> Self {
+        self.notes_subdir = normalize_subdir(subdir.as_ref());
+        self
+    }
+
+    /// Puts media (and everything else that's not a note) into given
+    /// subdirectory of the diary, e.g. `media`.
+    pub fn with_media_subdir(mut self, subdir: impl AsRef<str>) -> Self {
+        self.media_subdir = normalize_subdir(subdir.as_ref());
+        self
+    }
+
+    /// Whether files added into the diary should keep their original
+    /// modification time (enabled by default).
+    pub fn with_preserve_time(mut self, preserve_time: bool) -> Self {
+        self.preserve_time = preserve_time;
+        self
+    }
+
+    /// What `add()` should do when the destination file already exists -
+    /// only `OnConflict::Overwrite` lets it replace the file, other policies
+    /// are expected to be handled by the caller.
+    pub fn with_on_conflict(mut self, on_conflict: OnConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    pub fn with_copy_buffer_size(mut self, copy_buffer_size: usize) -> Self {
+        self.copy_buffer_size = copy_buffer_size;
+        self
+    }
+
+    /// Returns layout of the part of the diary given file belongs to - either
+    /// notes or media, which can live in separate subdirectories.
+    fn layout_of(&self, name: &str) -> Layout {
+        // Sidecars go together with their files
+        let name = name.strip_suffix(".tags").unwrap_or(name);
+
+        let kind = Path::new(name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| FileKind::from_ext(&ext.to_lowercase()));
+
+        if kind == Some(FileKind::Note) {
+            self.subdir_layout(&self.notes_subdir)
+        } else {
+            self.subdir_layout(&self.media_subdir)
+        }
+    }
+
+    fn subdir_layout(&self, subdir: &Option<String>) -> Layout {
+        match subdir {
+            Some(subdir) => self.layout.with_prefix(subdir),
+            None => self.layout.clone(),
+        }
+    }
+
+    /// Returns layouts of all parts of the diary, i.e. of notes and media.
+    fn layouts(&self) -> Vec<Layout> {
+        let notes = self.subdir_layout(&self.notes_subdir);
+        let media = self.subdir_layout(&self.media_subdir);
+
+        if notes == media {
+            vec![notes]
+        } else {
+            vec![notes, media]
+        }
+    }
+
+    /// Returns directories of given day - there are two of them when notes
+    /// and media live in separate subdirectories.
+    fn dirs(&self, date: NaiveDate) -> Vec<PathBuf> {
+        self.layouts()
+            .iter()
+            .map(|layout| self.dir.join(layout.render(date)))
+            .collect()
+    }
+
+    pub fn file_id(&self, date: NaiveDate, name: impl AsRef<str>) -> DiaryFileId {
+        let name = name.as_ref();
+
+        DiaryFileId {
+            date,
+            name: name.to_string(),
+            layout: self.layout_of(name),
+        }
     }
 
     pub fn file(&self, id: &DiaryFileId) -> PathBuf {
-        self.dir(id.date).join(&id.name)
+        self.dir.join(self.rel_file(id))
     }
 
-    pub fn add(&mut self, src: impl AsRef<Path>, dst: &DiaryFileId) -> Result<()> {
+    /// Returns path of given file, relative to the diary's root.
+    pub fn rel_file(&self, id: &DiaryFileId) -> PathBuf {
+        Path::new(&id.layout.render(id.date)).join(&id.name)
+    }
+
+    /// Whether the diary is stored on a remote (see [`RcloneBackend`]).
+    pub fn is_remote(&self) -> bool {
+        self.backend.local_dir().is_none()
+    }
+
+    /// Fails if the diary is stored on a remote, which supports only adding
+    /// new files.
+    fn ensure_local(&self) -> Result<()> {
+        if self.is_remote() {
+            return Err(anyhow!(
+                "`{}` is a remote diary, which doesn't support this operation",
+                self.dir.display()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns id of given media file's thumbnail, e.g.
+    /// `thumbs/12-34-56 DSC0001.jpg.jpg` (see `add --thumbnails`).
+    ///
+    /// The thumbnail keeps the file's full name, so that files differing only
+    /// by extension (e.g. RAW+JPEG pairs) don't share it.
+    pub fn thumbnail_id(&self, id: &DiaryFileId) -> DiaryFileId {
+        self.file_id(id.date, format!("{}/{}.jpg", THUMBS_DIR, id.name))
+    }
+
+    /// Returns id of the sidecar keeping given file's tags, e.g.
+    /// `12-34-56 DSC0001.jpg.tags` (see `add --tag`).
+    pub fn tags_id(&self, id: &DiaryFileId) -> DiaryFileId {
+        self.file_id(id.date, format!("{}.tags", id.name))
+    }
+
+    pub fn add(&mut self, src: impl AsRef<Path>, dst: &DiaryFileId, mode: AddMode) -> Result<()> {
+        self.add_with_progress(src, dst, mode, &mut |_| ())
+    }
+
+    /// Same as [`Self::add()`], but reports how many bytes have been copied
+    /// so far.
+    pub fn add_with_progress(
+        &mut self,
+        src: impl AsRef<Path>,
+        dst: &DiaryFileId,
+        mode: AddMode,
+        progress: &mut dyn FnMut(u64),
+    ) -> Result<()> {
         let src = src.as_ref();
-        let dir = self.dir(dst.date);
         let dst_path = self.file(dst);
+        let is_local = !self.is_remote();
+
+        // (remotes support only plain copies)
+        if !matches!(mode, AddMode::Copy | AddMode::Move) {
+            self.ensure_local()?;
+        }
+
+        if self.backend.has(&self.rel_file(dst))? {
+            if self.on_conflict != OnConflict::Overwrite {
+                return Err(anyhow!(
+                    "cannot add `{}` into diary, because it would overwrite `{}`",
+                    src.display(),
+                    dst,
+                ));
+            }
+
+            // (remotes get overwritten during the copy)
+            if is_local {
+                fs::remove_file(&dst_path)
+                    .with_context(|| format!("couldn't remove: {}", dst_path.display()))?;
+            }
+        }
+
+        self.backend
+            .mkdir(self.rel_file(dst).parent().unwrap_or(Path::new("")))?;
+
+        match mode {
+            AddMode::Copy | AddMode::Move => (),
+
+            AddMode::ConvertHeic => {
+                convert_heic_to_jpeg(src, &dst_path)?;
+
+                return self.copy_time(src, &dst_path);
+            }
+
+            AddMode::Symlink => {
+                let src = fs::canonicalize(src)
+                    .with_context(|| format!("couldn't resolve: {}", src.display()))?;
+
+                return symlink(&src, &dst_path).with_context(|| {
+                    format!(
+                        "couldn't link `{}` to `{}`",
+                        src.display(),
+                        dst_path.display()
+                    )
+                });
+            }
+
+            AddMode::Hardlink => {
+                return fs::hard_link(src, &dst_path).with_context(|| {
+                    format!(
+                        "couldn't link `{}` to `{}`",
+                        src.display(),
+                        dst_path.display()
+                    )
+                });
+            }
+        }
+
+        if mode == AddMode::Move && is_local {
+            match fs::rename(src, &dst_path) {
+                Ok(()) => return Ok(()),
+
+                // Source and diary are on different filesystems - fall back to
+                // copy + remove
+                Err(err) if err.kind() == io::ErrorKind::CrossesDevices => (),
+
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!(
+                            "couldn't move `{}` to `{}`",
+                            src.display(),
+                            dst_path.display()
+                        )
+                    });
+                }
+            }
+        }
+
+        self.backend
+            .add(src, &self.rel_file(dst), self.copy_buffer_size, progress)?;
+
+        // (rclone preserves modification time on its own)
+        if is_local {
+            self.copy_time(src, &dst_path)?;
+        }
+
+        if mode == AddMode::Move {
+            fs::remove_file(src).with_context(|| format!("couldn't remove: {}", src.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies modification time from `src` to `dst` (if enabled).
+    fn copy_time(&self, src: &Path, dst: &Path) -> Result<()> {
+        if !self.preserve_time {
+            return Ok(());
+        }
+
+        let mtime = FileTime::from_last_modification_time(&fs::metadata(src)?);
+
+        filetime::set_file_mtime(dst, mtime)
+            .with_context(|| format!("couldn't set modification time: {}", dst.display()))
+    }
+
+    pub fn create(&mut self, dst: &DiaryFileId, contents: &[u8]) -> Result<()> {
+        self.ensure_local()?;
+
+        let dst_path = self.file(dst);
+        let dir = dst_path.parent().unwrap();
 
         if dst_path.try_exists()? {
             return Err(anyhow!(
-                "cannot add `{}` into diary, because it would overwrite `{}`",
-                src.display(),
-                dst,
+                "cannot create `{}`, because it already exists",
+                dst
             ));
         }
 
         if !dir.try_exists()? {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        fs::write(&dst_path, contents)
+            .with_context(|| format!("couldn't write: {}", dst_path.display()))
+    }
+
+    /// Writes given file, replacing its contents if it already exists.
+    pub fn write(&mut self, dst: &DiaryFileId, contents: &[u8]) -> Result<()> {
+        self.ensure_local()?;
+
+        let dst_path = self.file(dst);
+        let dir = dst_path.parent().unwrap();
+
+        if !dir.try_exists()? {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        fs::write(&dst_path, contents)
+            .with_context(|| format!("couldn't write: {}", dst_path.display()))
+    }
+
+    pub fn append(&mut self, dst: &DiaryFileId, contents: &[u8]) -> Result<()> {
+        self.ensure_local()?;
+
+        let dst_path = self.file(dst);
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&dst_path)
+            .with_context(|| format!("couldn't open: {}", dst_path.display()))?;
+
+        file.write_all(contents)
+            .with_context(|| format!("couldn't write: {}", dst_path.display()))
+    }
+
+    pub fn read(&self, id: &DiaryFileId) -> Result<String> {
+        self.ensure_local()?;
+
+        let path = self.file(id);
+
+        fs::read_to_string(&path).with_context(|| format!("couldn't read: {}", path.display()))
+    }
+
+    /// Returns when `add --since-last-run` was last completed.
+    pub fn last_run(&self) -> Result<Option<DateTime<Local>>> {
+        self.ensure_local()?;
+
+        let path = self.dir.join(LAST_RUN_FILE);
+
+        if !path.try_exists()? {
+            return Ok(None);
+        }
+
+        let last_run = fs::read_to_string(&path)
+            .with_context(|| format!("couldn't read: {}", path.display()))?;
+
+        let last_run = DateTime::parse_from_rfc3339(last_run.trim())
+            .with_context(|| format!("couldn't parse: {}", path.display()))?;
+
+        Ok(Some(last_run.with_timezone(&Local)))
+    }
+
+    pub fn set_last_run(&mut self, last_run: DateTime<Local>) -> Result<()> {
+        self.ensure_local()?;
+
+        let path = self.dir.join(LAST_RUN_FILE);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        fs::write(&path, format!("{}\n", last_run.to_rfc3339()))
+            .with_context(|| format!("couldn't write: {}", path.display()))
+    }
+
+    /// Locks the diary for writing, failing if another import holds the lock;
+    /// the lock gets released when the returned guard is dropped.
+    ///
+    /// Remote diaries are not locked.
+    pub fn lock(&self) -> Result<Option<DiaryLock>> {
+        if self.is_remote() {
+            return Ok(None);
+        }
+
+        let path = self.dir.join(LOCK_FILE);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("couldn't open: {}", path.display()))?;
+
+        if let Err(err) = file.try_lock_exclusive() {
+            if err.kind() == fs2::lock_contended_error().kind() {
+                return Err(anyhow!(
+                    "diary is locked by another import, try again once it's finished: {}",
+                    path.display()
+                ));
+            }
+
+            return Err(err).with_context(|| format!("couldn't lock: {}", path.display()));
+        }
+
+        Ok(Some(DiaryLock { file }))
+    }
+
+    /// Checks whether files can be added into given directory (relative to the
+    /// diary's root) by creating it together with a file inside; everything
+    /// that's been created for the check gets removed afterwards.
+    pub fn check_writable(&self, rel_dir: &Path) -> Result<()> {
+        self.ensure_local()?;
+
+        let dir = self.dir.join(rel_dir);
+        let file = dir.join(WRITE_CHECK_FILE);
+
+        // Topmost directory that doesn't exist yet, i.e. the one to remove
+        let created = dir
+            .ancestors()
+            .take_while(|dir| !dir.exists())
+            .last()
+            .map(Path::to_owned);
+
+        let result: Result<_> = try {
             fs::create_dir_all(&dir)
                 .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&file)
+                .with_context(|| format!("couldn't create: {}", file.display()))?;
+
+            fs::remove_file(&file)
+                .with_context(|| format!("couldn't remove: {}", file.display()))?;
+        };
+
+        if let Some(created) = created {
+            if created.exists() {
+                fs::remove_dir_all(&created)
+                    .with_context(|| format!("couldn't remove: {}", created.display()))?;
+            }
+        }
+
+        result
+    }
+
+    /// Points the `latest` symlink (in diary's root) at directory of the newest
+    /// day present in the diary, returning that day.
+    pub fn link_latest(&mut self) -> Result<Option<NaiveDate>> {
+        let latest = self
+            .iter()?
+            .filter_map_ok(|file| match file {
+                FoundDiaryFile::Recognized(id) => Some(id),
+                FoundDiaryFile::Unrecognized(_) => None,
+            })
+            .fold_ok(None, |latest: Option<DiaryFileId>, id| match latest {
+                Some(latest) if latest.date >= id.date => Some(latest),
+                _ => Some(id),
+            })?;
+
+        let Some(latest) = latest else {
+            return Ok(None);
+        };
+
+        let link = self.dir.join(LATEST_LINK);
+
+        if let Ok(metadata) = fs::symlink_metadata(&link) {
+            if !metadata.is_symlink() {
+                return Err(anyhow!(
+                    "cannot update `{}`, because it's not a symlink",
+                    link.display()
+                ));
+            }
+
+            fs::remove_file(&link)
+                .with_context(|| format!("couldn't remove: {}", link.display()))?;
+        }
+
+        symlink_dir(latest.layout.render(latest.date), &link)
+            .with_context(|| format!("couldn't create symlink: {}", link.display()))?;
+
+        Ok(Some(latest.date))
+    }
+
+    /// Returns imports recorded through `add --journal`, oldest first.
+    pub fn journal(&self) -> Result<Vec<JournalEntry>> {
+        self.ensure_local()?;
+
+        let path = self.dir.join(JOURNAL_FILE);
+
+        if !path.try_exists()? {
+            return Ok(Vec::new());
+        }
+
+        fs::read_to_string(&path)
+            .with_context(|| format!("couldn't read: {}", path.display()))?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("couldn't parse: {}", path.display()))
+            })
+            .collect()
+    }
+
+    pub fn push_journal(&mut self, entry: &JournalEntry) -> Result<()> {
+        let mut journal = self.journal()?;
+
+        journal.push(entry.clone());
+
+        self.write_journal(&journal)
+    }
+
+    /// Removes the newest entry from the journal, returning it.
+    pub fn pop_journal(&mut self) -> Result<Option<JournalEntry>> {
+        let mut journal = self.journal()?;
+        let entry = journal.pop();
+
+        self.write_journal(&journal)?;
+
+        Ok(entry)
+    }
+
+    fn write_journal(&mut self, journal: &[JournalEntry]) -> Result<()> {
+        let path = self.dir.join(JOURNAL_FILE);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        let mut contents = String::new();
+
+        for entry in journal {
+            contents += &serde_json::to_string(entry)?;
+            contents.push('\n');
+        }
+
+        fs::write(&path, contents).with_context(|| format!("couldn't write: {}", path.display()))
+    }
+
+    /// Moves given source file into the diary's trash, under given name,
+    /// returning its new path (relative to the diary's root).
+    pub fn trash(&mut self, src: &Path, name: &str) -> Result<PathBuf> {
+        self.ensure_local()?;
+
+        let trash = Path::new(TRASH_DIR).join(name);
+
+        move_file(src, &self.dir.join(&trash))?;
+
+        Ok(trash)
+    }
+
+    /// Copies given diary file into the diary's trash, under given name, so
+    /// that its current version can be restored later; returns the copy's path
+    /// (relative to the diary's root).
+    pub fn backup(&mut self, id: &DiaryFileId, name: &str) -> Result<PathBuf> {
+        self.ensure_local()?;
+
+        let src = self.file(id);
+        let backup = Path::new(TRASH_DIR).join(name);
+        let dst = self.dir.join(&backup);
+
+        if let Some(dir) = dst.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        fs::copy(&src, &dst)
+            .with_context(|| format!("couldn't copy `{}` to `{}`", src.display(), dst.display()))?;
+
+        Ok(backup)
+    }
+
+    /// Puts file kept by [`Self::backup()`] back in place of given one (both
+    /// relative to the diary's root); returns whether the backup existed.
+    pub fn restore(&mut self, backup: &Path, rel_path: &Path) -> Result<bool> {
+        self.ensure_local()?;
+
+        let src = self.dir.join(backup);
+
+        if !src.try_exists()? {
+            return Ok(false);
+        }
+
+        move_file(&src, &self.dir.join(rel_path))?;
+        self.remove_empty_parents(backup)?;
+
+        Ok(true)
+    }
+
+    /// Moves file out of the diary (given relatively to the diary's root), e.g.
+    /// when restoring a trashed source file; returns whether it existed.
+    pub fn move_out(&mut self, rel_path: &Path, dst: &Path) -> Result<bool> {
+        self.ensure_local()?;
+
+        let src = self.dir.join(rel_path);
+
+        if !src.try_exists()? {
+            return Ok(false);
         }
 
-        fs::copy(src, &dst_path).with_context(|| {
-            format!(
-                "couldn't copy `{}` to `{}`",
-                src.display(),
-                dst_path.display()
-            )
-        })?;
+        if dst.try_exists()? {
+            return Err(anyhow!(
+                "cannot move `{}` out of the diary, because it would overwrite `{}`",
+                rel_path.display(),
+                dst.display(),
+            ));
+        }
+
+        move_file(&src, dst)?;
+        self.remove_empty_parents(rel_path)?;
+
+        Ok(true)
+    }
+
+    /// Removes file given relatively to the diary's root; returns whether it
+    /// existed.
+    pub fn remove_rel(&mut self, rel_path: &Path) -> Result<bool> {
+        self.ensure_local()?;
+
+        let path = self.dir.join(rel_path);
+
+        if !path.try_exists()? {
+            return Ok(false);
+        }
+
+        fs::remove_file(&path).with_context(|| format!("couldn't remove: {}", path.display()))?;
+        self.remove_empty_parents(rel_path)?;
+
+        Ok(true)
+    }
+
+    /// Removes directories leading to given file (relative to the diary's
+    /// root) which have been left empty, e.g. a day whose only file got
+    /// removed.
+    fn remove_empty_parents(&mut self, rel_path: &Path) -> Result<()> {
+        for rel_dir in rel_path.ancestors().skip(1) {
+            if rel_dir.as_os_str().is_empty() {
+                break;
+            }
+
+            let dir = self.dir.join(rel_dir);
+
+            if fs::read_dir(&dir)?.next().is_some() {
+                break;
+            }
+
+            fs::remove_dir(&dir).with_context(|| format!("couldn't remove: {}", dir.display()))?;
+        }
 
         Ok(())
     }
 
+    pub fn hash(&self, id: &DiaryFileId) -> Result<blake3::Hash> {
+        self.ensure_local()?;
+
+        hash_file(self.file(id))
+    }
+
+    pub fn remove(&mut self, id: &DiaryFileId) -> Result<()> {
+        self.ensure_local()?;
+
+        let path = self.file(id);
+
+        fs::remove_file(&path).with_context(|| format!("couldn't remove: {}", path.display()))
+    }
+
+    /// Returns directories (relative to the diary's root) of days, months etc.
+    /// which don't contain any files, children going before their parents.
+    ///
+    /// `.gitkeep` files don't count as contents.
+    pub fn empty_dirs(&self) -> Result<Vec<PathBuf>> {
+        self.ensure_local()?;
+
+        let mut dirs = Vec::new();
+
+        self.collect_empty_dirs(Path::new(""), &mut dirs)?;
+
+        Ok(dirs)
+    }
+
+    fn collect_empty_dirs(&self, rel_dir: &Path, dirs: &mut Vec<PathBuf>) -> Result<bool> {
+        let dir = self.dir.join(rel_dir);
+        let depth = rel_dir.components().count();
+
+        let max_depth = self
+            .layouts()
+            .iter()
+            .map(|layout| layout.depth())
+            .max()
+            .unwrap_or_default();
+
+        let mut entries: Vec<_> = fs::read_dir(&dir)
+            .with_context(|| format!("couldn't read directory: {}", dir.display()))?
+            .collect::<Result<_, _>>()?;
+
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut is_empty = true;
+
+        for entry in entries {
+            let name = entry.file_name();
+
+            if name == ".gitkeep" {
+                continue;
+            }
+
+            let is_hidden = name.to_string_lossy().starts_with('.');
+
+            let is_dir = depth < max_depth && !is_hidden && entry.file_type()?.is_dir();
+
+            if is_dir && self.collect_empty_dirs(&rel_dir.join(&name), dirs)? {
+                continue;
+            }
+
+            is_empty = false;
+        }
+
+        if is_empty && depth > 0 {
+            dirs.push(rel_dir.to_owned());
+        }
+
+        Ok(is_empty)
+    }
+
+    /// Removes directory returned from [`Self::empty_dirs()`].
+    pub fn remove_empty_dir(&mut self, rel_dir: &Path) -> Result<()> {
+        self.ensure_local()?;
+
+        let dir = self.dir.join(rel_dir);
+        let gitkeep = dir.join(".gitkeep");
+
+        if gitkeep.try_exists()? {
+            fs::remove_file(&gitkeep)
+                .with_context(|| format!("couldn't remove: {}", gitkeep.display()))?;
+        }
+
+        fs::remove_dir(&dir).with_context(|| format!("couldn't remove: {}", dir.display()))
+    }
+
+    /// Moves file within the diary (e.g. into another day).
+    pub fn rename(&mut self, src: &DiaryFileId, dst: &DiaryFileId) -> Result<()> {
+        self.ensure_local()?;
+
+        let src_path = self.file(src);
+        let dst_path = self.file(dst);
+
+        if dst_path.try_exists()? {
+            return Err(anyhow!(
+                "cannot move `{}`, because it would overwrite `{}`",
+                src,
+                dst
+            ));
+        }
+
+        if let Some(dir) = dst_path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+        }
+
+        fs::rename(&src_path, &dst_path)
+            .with_context(|| format!("couldn't move `{}` to `{}`", src, dst))
+    }
+
     pub fn has(&self, id: &DiaryFileId) -> Result<bool> {
-        Ok(self.file(id).try_exists()?)
+        self.backend.has(&self.rel_file(id))
+    }
+
+    pub fn hashes(&self, date: NaiveDate) -> Result<HashSet<blake3::Hash>> {
+        self.ensure_local()?;
+
+        let mut hashes = HashSet::new();
+
+        for dir in self.dirs(date) {
+            if !dir.try_exists()? {
+                continue;
+            }
+
+            // Files nested within the day (bursts, events etc.) count, too -
+            // but not thumbnails and tag sidecars, which aren't imported media
+            for path in glob(&format!("{}/**/*", dir.display()))? {
+                let path = path?;
+
+                let Ok(rel_path) = path.strip_prefix(&dir) else {
+                    continue;
+                };
+
+                let is_hidden = rel_path
+                    .components()
+                    .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+
+                let is_thumbnail = rel_path.starts_with(THUMBS_DIR);
+                let is_tags = path.extension().is_some_and(|ext| ext == "tags");
+
+                if path.is_file() && !is_hidden && !is_thumbnail && !is_tags {
+                    hashes.insert(hash_file(path)?);
+                }
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<FoundDiaryFile>> + '_> {
+        self.ensure_local()?;
+
+        let files = glob(&format!("{}/**/*", self.dir.display()))?
+            .filter_ok(|entry| entry.is_file())
+            .map(|entry| {
+                let path = entry?;
+
+                let Ok(rel_path) = path.strip_prefix(&self.dir) else {
+                    return Ok(None);
+                };
+
+                let is_hidden = rel_path
+                    .components()
+                    .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+
+                let is_thumbnail = self.layouts().iter().any(|layout| {
+                    rel_path
+                        .components()
+                        .nth(layout.depth())
+                        .is_some_and(|component| component.as_os_str() == THUMBS_DIR)
+                });
+
+                let is_latest_link = rel_path.starts_with(LATEST_LINK);
+
+                if is_hidden
+                    || is_thumbnail
+                    || is_latest_link
+                    || rel_path == Path::new(Config::FILE_NAME)
+                {
+                    return Ok(None);
+                }
+
+                if let Some(id) = self.identify(rel_path) {
+                    Ok(Some(FoundDiaryFile::Recognized(id)))
+                } else {
+                    Ok(Some(FoundDiaryFile::Unrecognized(path)))
+                }
+            })
+            .flatten_ok();
+
+        Ok(files)
+    }
+
+    fn identify(&self, rel_path: &Path) -> Option<DiaryFileId> {
+        let components: Vec<_> = rel_path
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<_>>()?;
+
+        let (name, dir) = components.split_last()?;
+
+        // File is identified with the layout it's been found in, even if it
+        // belongs to the other one (e.g. a note among media)
+        self.layouts().into_iter().find_map(|layout| {
+            let (dir, subdirs) = dir.split_at_checked(layout.depth())?;
+
+            // Files can be nested within the day - e.g. burst sequences are
+            // kept in `burst/<name>/`, events in `event-<n>/` and, with `add
+            // --keep-structure`, the source's own directories are mirrored
+            let name = subdirs.iter().chain([name]).join("/");
+
+            let date = layout.parse(&dir.join("/"))?;
+
+            Some(DiaryFileId { date, name, layout })
+        })
     }
 }
 
+fn normalize_subdir(subdir: &str) -> Option<String> {
+    let subdir = subdir.trim_matches('/');
+
+    (!subdir.is_empty()).then(|| subdir.to_owned())
+}
+
+/// Lock held over the diary (see [`DiaryRepository::lock()`]).
 #[derive(Debug)]
+pub struct DiaryLock {
+    file: File,
+}
+
+impl Drop for DiaryLock {
+    fn drop(&mut self) {
+        // The lock file itself stays in place - removing it would let another
+        // import lock a fresh file while someone's still waiting on this one
+        _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[derive(Debug)]
+pub enum FoundDiaryFile {
+    Recognized(DiaryFileId),
+    Unrecognized(PathBuf),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddMode {
+    Copy,
+    Move,
+    Symlink,
+    Hardlink,
+    ConvertHeic,
+}
+
+/// What to do when a file is about to be added into the diary, but its
+/// destination is already taken by a different file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnConflict {
+    /// Leave the existing file alone and don't add the new one
+    #[default]
+    Skip,
+
+    /// Replace the existing file
+    Overwrite,
+
+    /// Add the new file under a different name (`name (2)`, `name (3)` etc.)
+    Rename,
+
+    /// Abort the import
+    Fail,
+}
+
+#[derive(Clone, Debug)]
 pub struct DiaryFileId {
     pub date: NaiveDate,
     pub name: String,
+    layout: Layout,
 }
 
 impl DiaryFileId {
-    pub fn new(date: NaiveDate, name: impl AsRef<str>) -> Self {
-        Self {
-            date,
-            name: name.as_ref().to_string(),
-        }
+    pub fn kind(&self) -> Option<FileKind> {
+        let ext = Path::new(&self.name).extension()?.to_str()?.to_lowercase();
+
+        FileKind::from_ext(&ext)
     }
 }
 
 impl fmt::Display for DiaryFileId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "diary:{:04}/{:02}/{:02}/{}",
-            self.date.year(),
-            self.date.month(),
-            self.date.day(),
-            self.name
-        )
+        if self.layout.is_flat() {
+            write!(f, "diary:{}", self.name)
+        } else {
+            write!(f, "diary:{}/{}", self.layout.render(self.date), self.name)
+        }
+    }
+}
+
+impl Serialize for DiaryFileId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
     }
 }
@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+
+/// Extensions rewritten by `add --normalize-ext`, unless overridden through
+/// `--ext-alias` or `ext_aliases` in the config.
+pub const DEFAULT_EXT_ALIASES: &[(&str, &str)] = &[("jpeg", "jpg"), ("heif", "heic")];
+
+/// Parses an alias given through `--ext-alias`, e.g. `jpeg=jpg`.
+pub fn parse_ext_alias(s: &str) -> Result<(String, String)> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("invalid extension alias `{}` (expected `from=to`)", s))?;
+
+    for ext in [from, to] {
+        if ext.is_empty() || !ext.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            return Err(anyhow!(
+                "invalid extension alias `{}` (extensions may contain only letters and digits)",
+                s
+            ));
+        }
+    }
+
+    Ok((from.to_lowercase(), to.to_lowercase()))
+}
+
+/// Returns the canonical spelling of given (lowercase) extension, looking at
+/// `aliases` first and [`DEFAULT_EXT_ALIASES`] second.
+pub fn normalize_ext<'a>(ext: &'a str, aliases: &'a [(String, String)]) -> &'a str {
+    let alias = aliases
+        .iter()
+        .rev()
+        .find(|(from, _)| from == ext)
+        .map(|(_, to)| to.as_str());
+
+    alias
+        .or_else(|| {
+            DEFAULT_EXT_ALIASES
+                .iter()
+                .find(|(from, _)| *from == ext)
+                .map(|(_, to)| *to)
+        })
+        .unwrap_or(ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("jpeg=jpg", Some(("jpeg", "jpg")))]
+    #[test_case("JPEG=JPG", Some(("jpeg", "jpg")) ; "uppercase")]
+    #[test_case("jpeg", None ; "missing target")]
+    #[test_case("jpeg=", None ; "empty target")]
+    #[test_case("=jpg", None ; "empty source")]
+    #[test_case("jpeg=.jpg", None ; "dot")]
+    fn parse(alias: &str, expected: Option<(&str, &str)>) {
+        let expected = expected.map(|(from, to)| (from.to_owned(), to.to_owned()));
+
+        assert_eq!(expected, parse_ext_alias(alias).ok());
+    }
+
+    #[test_case("jpeg", &[], "jpg")]
+    #[test_case("heif", &[], "heic")]
+    #[test_case("png", &[], "png")]
+    #[test_case("jpeg", &[("jpeg", "jpeg")], "jpeg" ; "overridden default")]
+    #[test_case("jpg", &[("jpg", "jpeg")], "jpeg" ; "custom alias")]
+    #[test_case("jpg", &[("jpg", "jpeg"), ("jpg", "jpe")], "jpe" ; "last alias wins")]
+    fn normalize(ext: &str, aliases: &[(&str, &str)], expected: &str) {
+        let aliases: Vec<_> = aliases
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect();
+
+        assert_eq!(expected, normalize_ext(ext, &aliases));
+    }
+}
@@ -0,0 +1,35 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FileKind {
+    Note,
+    Photo,
+    Video,
+    Audio,
+}
+
+impl FileKind {
+    pub fn from_ext(ext: &str) -> Option<Self> {
+        match ext {
+            "org" | "md" | "txt" => Some(Self::Note),
+            "jpg" | "jpeg" | "png" | "heic" | "heif" => Some(Self::Photo),
+            ext if Self::is_animatable(ext) => Some(Self::Photo),
+            ext if Self::is_raw(ext) => Some(Self::Photo),
+            "mov" | "mp4" | "webm" => Some(Self::Video),
+            "mp3" | "m4a" | "wav" | "flac" | "ogg" => Some(Self::Audio),
+            _ => None,
+        }
+    }
+
+    /// Returns whether given extension belongs to an image format that can be
+    /// animated - animated images are imported as videos (see
+    /// [`crate::utils::is_animated()`]).
+    pub fn is_animatable(ext: &str) -> bool {
+        matches!(ext, "gif" | "webp" | "avif")
+    }
+
+    /// Returns whether given extension belongs to a RAW photo.
+    pub fn is_raw(ext: &str) -> bool {
+        matches!(ext, "arw" | "cr2" | "cr3" | "nef" | "dng" | "raf" | "orf")
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::process::Command;
+
+/// Place where a photo was taken, in decimal degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsCoords {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// Reads `GPSLatitude` and `GPSLongitude` of given photo.
+///
+/// Returns `None` when the photo doesn't have GPS data.
+pub fn extract_gps(path: &Path) -> Result<Option<GpsCoords>> {
+    if let Some(coords) = extract_exif_gps(path) {
+        return Ok(coords);
+    }
+
+    let out = Command::new("exiftool")
+        .arg("-s3")
+        .arg("-n")
+        .arg("-GPSLatitude")
+        .arg("-GPSLongitude")
+        .arg(path)
+        .output()
+        .context("couldn't launch exiftool")?
+        .stdout;
+
+    let out = String::from_utf8_lossy(&out);
+    let mut lines = out.lines().map(str::trim);
+
+    let (Some(lat), Some(lon)) = (lines.next(), lines.next()) else {
+        return Ok(None);
+    };
+
+    let parse = |value: &str| -> Result<f64> {
+        value
+            .parse()
+            .with_context(|| format!("couldn't parse exiftool's response: {}", value))
+    };
+
+    Ok(Some(GpsCoords {
+        lat: parse(lat)?,
+        lon: parse(lon)?,
+    }))
+}
+
+/// Reads GPS coordinates in-process, without spawning exiftool.
+///
+/// Returns `None` when the file's format is not supported by the embedded
+/// reader, in which case the caller should fall back to exiftool.
+fn extract_exif_gps(path: &Path) -> Option<Option<GpsCoords>> {
+    let file = File::open(path).ok()?;
+
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    let coord = |tag: exif::Tag, ref_tag: exif::Tag, negative_ref: u8| -> Option<f64> {
+        let field = exif.get_field(tag, exif::In::PRIMARY)?;
+
+        let exif::Value::Rational(parts) = &field.value else {
+            return None;
+        };
+
+        let [deg, min, sec] = parts.as_slice() else {
+            return None;
+        };
+
+        let is_negative = exif
+            .get_field(ref_tag, exif::In::PRIMARY)
+            .is_some_and(|field| match &field.value {
+                exif::Value::Ascii(values) => values
+                    .first()
+                    .is_some_and(|value| value.first() == Some(&negative_ref)),
+                _ => false,
+            });
+
+        Some(to_decimal(
+            deg.to_f64(),
+            min.to_f64(),
+            sec.to_f64(),
+            is_negative,
+        ))
+    };
+
+    let lat = coord(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b'S');
+    let lon = coord(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b'W');
+
+    Some(lat.zip(lon).map(|(lat, lon)| GpsCoords { lat, lon }))
+}
+
+fn to_decimal(deg: f64, min: f64, sec: f64, is_negative: bool) -> f64 {
+    let value = deg + min / 60.0 + sec / 3600.0;
+
+    if is_negative {
+        -value
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    #[test_case(50.0, 3.0, 36.0, false, 50.06)]
+    #[test_case(19.0, 56.0, 24.0, true, -19.94)]
+    #[test_case(0.0, 0.0, 0.0, false, 0.0)]
+    fn to_decimal(deg: f64, min: f64, sec: f64, is_negative: bool, expected: f64) {
+        let actual = super::to_decimal(deg, min, sec, is_negative);
+
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+pub fn hash_file(path: impl AsRef<Path>) -> Result<blake3::Hash> {
+    let path = path.as_ref();
+
+    let file: Result<_> = try {
+        let mut hasher = blake3::Hasher::new();
+
+        hasher.update_reader(File::open(path)?)?;
+        hasher.finalize()
+    };
+
+    file.with_context(|| format!("couldn't hash file: {}", path.display()))
+}
+
+/// Algorithm used by hash manifests (see `add --hash-manifest`); manifests are
+/// laid out like the output of the corresponding `*sum` tool (e.g.
+/// `sha256sum`), so that they can be checked with it as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ManifestFormat {
+    #[default]
+    Blake3,
+    Sha256,
+    Md5,
+}
+
+impl ManifestFormat {
+    /// Returns the hex-encoded hash of given file.
+    pub fn hash_file(self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+
+        let hash = match self {
+            ManifestFormat::Blake3 => return Ok(hash_file(path)?.to_string()),
+            ManifestFormat::Sha256 => File::open(path).and_then(hash_reader::<Sha256>),
+            ManifestFormat::Md5 => File::open(path).and_then(hash_reader::<Md5>),
+        };
+
+        hash.with_context(|| format!("couldn't hash file: {}", path.display()))
+    }
+
+    /// Returns whether given string looks like a hash produced by this
+    /// algorithm.
+    pub fn is_hash(self, hash: &str) -> bool {
+        let len = match self {
+            ManifestFormat::Blake3 | ManifestFormat::Sha256 => 64,
+            ManifestFormat::Md5 => 32,
+        };
+
+        hash.len() == len && hash.chars().all(|ch| ch.is_ascii_hexdigit())
+    }
+}
+
+fn hash_reader<D>(mut reader: impl io::Read) -> io::Result<String>
+where
+    D: Digest + io::Write,
+{
+    let mut hasher = D::new();
+
+    io::copy(&mut reader, &mut hasher)?;
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hash, byte| {
+            _ = write!(hash, "{:02x}", byte);
+            hash
+        });
+
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(
+        ManifestFormat::Sha256,
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    )]
+    #[test_case(ManifestFormat::Md5, "d41d8cd98f00b204e9800998ecf8427e")]
+    fn hash_empty(format: ManifestFormat, expected: &str) {
+        let actual = match format {
+            ManifestFormat::Sha256 => hash_reader::<Sha256>(io::empty()),
+            ManifestFormat::Md5 => hash_reader::<Md5>(io::empty()),
+            ManifestFormat::Blake3 => unreachable!(),
+        };
+
+        assert_eq!(expected, actual.unwrap());
+        assert!(format.is_hash(expected));
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// Record of what a single `diary add --journal` has changed, so that it can be
+/// reverted with `diary undo`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub actions: Vec<JournalAction>,
+}
+
+/// Paths inside the diary are relative to its root, while paths of source
+/// files are kept as given on the command line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalAction {
+    /// File has been created in the diary (copied, linked, converted etc.)
+    Added { dst: PathBuf },
+
+    /// File has been moved from the source into the diary
+    Moved { src: PathBuf, dst: PathBuf },
+
+    /// Source file has been moved into diary's trash, instead of being removed
+    Trashed { src: PathBuf, trash: PathBuf },
+
+    /// File that already was in the diary has been changed (overwritten,
+    /// appended to, tagged etc.), with its previous version kept in diary's
+    /// trash
+    Modified { dst: PathBuf, backup: PathBuf },
+}
+
+/// Moves file, creating destination's parent directories if needed; works
+/// across filesystems, too.
+pub fn move_file(src: &Path, dst: &Path) -> Result<()> {
+    if let Some(dir) = dst.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+    }
+
+    match fs::rename(src, dst) {
+        Ok(()) => return Ok(()),
+
+        // Source and destination are on different filesystems - fall back to
+        // copy + remove
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => (),
+
+        Err(err) => {
+            return Err(err).with_context(|| {
+                format!("couldn't move `{}` to `{}`", src.display(), dst.display())
+            });
+        }
+    }
+
+    fs::copy(src, dst)
+        .with_context(|| format!("couldn't copy `{}` to `{}`", src.display(), dst.display()))?;
+
+    let mtime = FileTime::from_last_modification_time(&fs::metadata(src)?);
+
+    filetime::set_file_mtime(dst, mtime)
+        .with_context(|| format!("couldn't set modification time: {}", dst.display()))?;
+
+    fs::remove_file(src).with_context(|| format!("couldn't remove: {}", src.display()))
+}
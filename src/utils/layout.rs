@@ -0,0 +1,202 @@
+use anyhow::{anyhow, Error, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Template describing where each day lives inside the diary, e.g.
+/// `{year}/{month}/{day}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    template: Arc<str>,
+}
+
+impl Layout {
+    /// Layout which puts all files straight into the diary's root, without
+    /// any per-day directories (see `add --flatten`).
+    pub fn flat() -> Self {
+        Self {
+            template: "".into(),
+        }
+    }
+
+    /// Returns this layout nested within given subdirectory of the diary,
+    /// e.g. `journal/{year}/{month}/{day}` (see `--notes-subdir`).
+    pub fn with_prefix(&self, prefix: &str) -> Self {
+        let template = if self.is_flat() {
+            prefix.to_owned()
+        } else {
+            format!("{}/{}", prefix, self.template)
+        };
+
+        Self {
+            template: template.into(),
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.template.is_empty()
+    }
+
+    #[allow(clippy::literal_string_with_formatting_args)]
+    pub fn render(&self, date: NaiveDate) -> String {
+        self.template
+            .replace("{year}", &format!("{:04}", date.year()))
+            .replace("{month}", &format!("{:02}", date.month()))
+            .replace("{day}", &format!("{:02}", date.day()))
+    }
+
+    /// Reverses [`Self::render()`], returning the date a directory (relative
+    /// to the diary's root) corresponds to.
+    #[allow(clippy::literal_string_with_formatting_args)]
+    pub fn parse(&self, dir: &str) -> Option<NaiveDate> {
+        let mut template = &*self.template;
+        let mut dir = dir;
+        let mut year = None;
+        let mut month = None;
+        let mut day = None;
+
+        while !template.is_empty() {
+            let (slot, len) = if let Some(rest) = template.strip_prefix("{year}") {
+                template = rest;
+                (&mut year, 4)
+            } else if let Some(rest) = template.strip_prefix("{month}") {
+                template = rest;
+                (&mut month, 2)
+            } else if let Some(rest) = template.strip_prefix("{day}") {
+                template = rest;
+                (&mut day, 2)
+            } else {
+                let ch = template.chars().next()?;
+
+                template = &template[ch.len_utf8()..];
+                dir = dir.strip_prefix(ch)?;
+
+                continue;
+            };
+
+            let value = dir.get(0..len)?;
+
+            if !value.chars().all(|ch| ch.is_ascii_digit()) {
+                return None;
+            }
+
+            let value: u32 = value.parse().ok()?;
+
+            if slot.is_some_and(|slot| slot != value) {
+                return None;
+            }
+
+            *slot = Some(value);
+            dir = &dir[len..];
+        }
+
+        if !dir.is_empty() {
+            return None;
+        }
+
+        NaiveDate::from_ymd_opt(year? as i32, month?, day?)
+    }
+
+    /// Number of path components a day's directory consists of.
+    pub fn depth(&self) -> usize {
+        if self.is_flat() {
+            0
+        } else {
+            self.template.split('/').count()
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            template: "{year}/{month}/{day}".into(),
+        }
+    }
+}
+
+impl FromStr for Layout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim_matches('/');
+
+        for token in ["{year}", "{month}", "{day}"] {
+            if !s.contains(token) {
+                return Err(anyhow!("layout is missing `{}`: {}", token, s));
+            }
+        }
+
+        if s.split('/').any(|component| component.is_empty()) {
+            return Err(anyhow!("layout contains an empty path component: {}", s));
+        }
+
+        Ok(Self { template: s.into() })
+    }
+}
+
+impl Serialize for Layout {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Layout {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("{year}/{month}/{day}", "2018/01/02")]
+    #[test_case("{year}/{year}-{month}-{day}", "2018/2018-01-02")]
+    #[test_case("{year}-{month}-{day}", "2018-01-02"; "flat")]
+    fn roundtrip(layout: &str, expected: &str) {
+        let layout: Layout = layout.parse().unwrap();
+        let date = NaiveDate::from_ymd_opt(2018, 1, 2).unwrap();
+
+        assert_eq!(expected, layout.render(date));
+        assert_eq!(Some(date), layout.parse(expected));
+    }
+
+    #[test]
+    fn with_prefix() {
+        let layout = Layout::default().with_prefix("media/photos");
+        let date = NaiveDate::from_ymd_opt(2018, 1, 2).unwrap();
+
+        assert_eq!("media/photos/2018/01/02", layout.render(date));
+        assert_eq!(Some(date), layout.parse("media/photos/2018/01/02"));
+        assert_eq!(None, layout.parse("journal/2018/01/02"));
+        assert_eq!(5, layout.depth());
+    }
+
+    #[test_case("{year}/{month}/{day}", "2018/01")]
+    #[test_case("{year}/{month}/{day}", "2018/1/2")]
+    #[test_case("{year}/{month}/{day}", "2018/13/02")]
+    #[test_case("{year}/{year}-{month}-{day}", "2018/2019-01-02")]
+    fn parse_invalid(layout: &str, given: &str) {
+        let layout: Layout = layout.parse().unwrap();
+
+        assert_eq!(None, layout.parse(given));
+    }
+}
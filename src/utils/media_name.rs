@@ -0,0 +1,151 @@
+use crate::utils::NameTemplate;
+use chrono::{NaiveDateTime, Timelike};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Name prefixes of screenshots, as used by various systems and languages.
+const SCREENSHOT_PREFIXES: &[&str] = &[
+    "screenshot",
+    "screen shot",
+    "bildschirmfoto",
+    "capture d'écran",
+    "capture d’écran",
+    "captura de pantalla",
+    "schermafbeelding",
+    "zrzut ekranu",
+];
+
+/// Name prefixes of screen recordings, as used by various systems and
+/// languages.
+const SCREENCAST_PREFIXES: &[&str] = &[
+    "screencast",
+    "screen recording",
+    "bildschirmaufnahme",
+    "enregistrement de l'écran",
+    "enregistrement de l’écran",
+    "grabación de pantalla",
+    "nagranie ekranu",
+];
+
+/// Returns what kind of capture given file is (e.g. `screenshot`), judging
+/// by its name; prefixes are matched case-insensitively.
+pub fn get_media_label(
+    stem: &str,
+    extra_screenshot_prefixes: &[String],
+    extra_screencast_prefixes: &[String],
+) -> Option<&'static str> {
+    let stem = stem.to_lowercase();
+
+    let matches = |builtin: &[&str], extra: &[String]| {
+        builtin
+            .iter()
+            .copied()
+            .chain(extra.iter().map(String::as_str))
+            .any(|prefix| stem.starts_with(&prefix.to_lowercase()))
+    };
+
+    if matches(SCREENSHOT_PREFIXES, extra_screenshot_prefixes) {
+        return Some("screenshot");
+    }
+
+    if matches(SCREENCAST_PREFIXES, extra_screencast_prefixes) {
+        return Some("screencast");
+    }
+
+    if stem.starts_with("recording") {
+        return Some("recording");
+    }
+
+    None
+}
+
+/// Returns base name of a burst-mode sequence given photo belongs to, e.g.
+/// `IMG_1234` for `IMG_1234_BURST001`; matched case-insensitively.
+pub fn get_burst_name(stem: &str) -> Option<&str> {
+    static REGEX: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i)^(.+?)[_-]BURST\d+(?:[_-].*)?$").unwrap());
+
+    Some(REGEX.captures(stem)?.get(1)?.as_str())
+}
+
+/// Returns name (without extension) under which media file gets stored in the
+/// diary - e.g. `12-34-56 DSC0001` or, with a template, whatever it renders to.
+///
+/// Falls back to file's original stem when there's nothing better to use.
+pub fn get_media_name(
+    stem: &str,
+    dt: NaiveDateTime,
+    id: Option<&str>,
+    label: Option<&str>,
+    template: Option<&NameTemplate>,
+) -> String {
+    if let Some(template) = template {
+        let name = template.render(dt, stem, id, label);
+
+        return if name.is_empty() {
+            stem.to_owned()
+        } else {
+            name
+        };
+    }
+
+    let time = format!("{:02}-{:02}-{:02}", dt.hour(), dt.minute(), dt.second());
+
+    // (label goes first, since for names such as `Screenshot 2018-01-02 at
+    // 12.34.56` the id is just the screenshot's prefix)
+    if let Some(label) = label {
+        return format!("{} {}", time, label);
+    }
+
+    if let Some(id) = id {
+        return format!("{} {}", time, id);
+    }
+
+    stem.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("Screenshot 2018-01-02 at 12.34.56", Some("screenshot"))]
+    #[test_case("Screenshot_20180102_123456", Some("screenshot"); "kde")]
+    #[test_case("Screen Shot 2018-01-02 at 12.34.56", Some("screenshot"); "macos")]
+    #[test_case("Bildschirmfoto 2018-01-02 um 12.34.56", Some("screenshot"); "german")]
+    #[test_case("screenshot_2018", Some("screenshot"); "lowercase")]
+    #[test_case("Screen Recording 2018-01-02 at 12.34.56", Some("screencast"))]
+    #[test_case("Bildschirmaufnahme 2018-01-02", Some("screencast"); "german screencast")]
+    #[test_case("Recording 2018-01-02", Some("recording"))]
+    #[test_case("Skärmbild 2018-01-02", Some("screenshot"); "extra")]
+    #[test_case("IMG_1234", None)]
+    fn get_media_label(stem: &str, expected: Option<&str>) {
+        let extra = vec!["Skärmbild".to_string()];
+
+        assert_eq!(expected, super::get_media_label(stem, &extra, &[]));
+    }
+
+    #[test_case("IMG_1234_BURST001", Some("IMG_1234"))]
+    #[test_case("IMG_1234_BURST20180102123456_COVER", Some("IMG_1234"); "cover")]
+    #[test_case("img_1234-burst002", Some("img_1234"); "lowercase")]
+    #[test_case("IMG_1234", None)]
+    #[test_case("BURST001", None; "missing base")]
+    #[test_case("IMG_1234_BURSTY", None; "missing number")]
+    fn get_burst_name(stem: &str, expected: Option<&str>) {
+        assert_eq!(expected, super::get_burst_name(stem));
+    }
+
+    #[test_case("IMG_1234", None, None, "IMG_1234")]
+    #[test_case("IMG_1234", Some("1234"), None, "12-34-56 1234")]
+    #[test_case(
+        "Screenshot",
+        Some("Screenshot"),
+        Some("screenshot"),
+        "12-34-56 screenshot"
+    )]
+    fn get_media_name(stem: &str, id: Option<&str>, label: Option<&str>, expected: &str) {
+        let dt = NaiveDateTime::parse_from_str("2018-01-02 12:34:56", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(expected, super::get_media_name(stem, dt, id, label, None));
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Error, Result};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Template describing how media files get named inside the diary, e.g.
+/// `{year}{month}{day}-{hour}{minute}{second}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    template: Arc<str>,
+}
+
+impl NameTemplate {
+    const TOKENS: &'static [&'static str] = &[
+        "{date}", "{time}", "{year}", "{month}", "{day}", "{hour}", "{minute}", "{second}", "{id}",
+        "{stem}", "{label}",
+    ];
+
+    /// Renders the name; tokens that have no value (e.g. `{id}` for a file
+    /// without an id) are replaced with nothing.
+    #[allow(clippy::literal_string_with_formatting_args)]
+    pub fn render(
+        &self,
+        dt: NaiveDateTime,
+        stem: &str,
+        id: Option<&str>,
+        label: Option<&str>,
+    ) -> String {
+        let name = self
+            .template
+            .replace("{date}", &dt.date().to_string())
+            .replace(
+                "{time}",
+                &format!("{:02}-{:02}-{:02}", dt.hour(), dt.minute(), dt.second()),
+            )
+            .replace("{year}", &format!("{:04}", dt.year()))
+            .replace("{month}", &format!("{:02}", dt.month()))
+            .replace("{day}", &format!("{:02}", dt.day()))
+            .replace("{hour}", &format!("{:02}", dt.hour()))
+            .replace("{minute}", &format!("{:02}", dt.minute()))
+            .replace("{second}", &format!("{:02}", dt.second()))
+            .replace("{id}", id.unwrap_or_default())
+            .replace("{stem}", stem)
+            .replace("{label}", label.unwrap_or_default());
+
+        name.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.contains('/') {
+            return Err(anyhow!("name template must not contain `/`: {}", s));
+        }
+
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow!("name template has an unclosed token: {}", s))?;
+
+            let token = &rest[start..start + end + 1];
+
+            if !Self::TOKENS.contains(&token) {
+                return Err(anyhow!(
+                    "name template has unknown token `{}`: {}",
+                    token,
+                    s
+                ));
+            }
+
+            rest = &rest[start + end + 1..];
+        }
+
+        Ok(Self { template: s.into() })
+    }
+}
+
+impl Serialize for NameTemplate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for NameTemplate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("{stem}", None, None, "IMG_1234")]
+    #[test_case(
+        "{year}{month}{day}-{hour}{minute}{second}",
+        None,
+        None,
+        "20180102-123456"
+    )]
+    #[test_case("{date} {time}", None, None, "2018-01-02 12-34-56")]
+    #[test_case("{time} {id}", Some("abcd"), None, "12-34-56 abcd")]
+    #[test_case("{time} {id} {label}", None, Some("screenshot"), "12-34-56 screenshot")]
+    fn render(template: &str, id: Option<&str>, label: Option<&str>, expected: &str) {
+        let template: NameTemplate = template.parse().unwrap();
+
+        let dt = NaiveDateTime::parse_from_str("2018-01-02 12:34:56", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(expected, template.render(dt, "IMG_1234", id, label));
+    }
+
+    #[test_case("{year}/{stem}")]
+    #[test_case("{stem")]
+    #[test_case("{nope}")]
+    fn parse_invalid(template: &str) {
+        assert!(template.parse::<NameTemplate>().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use regex::{Captures, Regex};
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Org links, e.g. `[[file:photo.jpg]]` or `[[./photo.jpg][Photo]]`.
+static ORG_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[(file:)?([^\]\[]+)\](?:\[[^\]\[]*\])?\]").unwrap());
+
+/// Markdown links and images, e.g. `![Photo](photo.jpg "title")` or
+/// `![Photo](<my photo.jpg>)` (the latter for targets containing spaces).
+static MD_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\]\((?:<([^<>\n]+)>|([^)\s]+))(?:\s+"[^"]*")?\)"#).unwrap());
+
+/// Returns targets of links pointing at local files (as written in the note,
+/// so relative to it), in order of their appearance; `ext` is note's extension.
+pub fn find_note_links(contents: &str, ext: &str) -> Vec<String> {
+    let mut links = Vec::new();
+
+    for_each_link(contents, ext, |link| {
+        if !links.iter().any(|l| l == link) {
+            links.push(link.to_owned());
+        }
+
+        None
+    });
+
+    links
+}
+
+/// Replaces link targets according to given `(from, to)` pairs.
+pub fn rewrite_note_links(contents: &str, ext: &str, links: &[(String, String)]) -> String {
+    for_each_link(contents, ext, |link| {
+        links
+            .iter()
+            .find(|(from, _)| from == link)
+            .map(|(_, to)| to.clone())
+    })
+}
+
+fn for_each_link(contents: &str, ext: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let (regex, groups): (_, &[usize]) = match ext {
+        "org" => (&*ORG_LINK, &[2]),
+        "md" => (&*MD_LINK, &[1, 2]),
+        _ => return contents.to_owned(),
+    };
+
+    regex
+        .replace_all(contents, |caps: &Captures| {
+            let link = caps.get(0).unwrap();
+            let target = groups.iter().find_map(|&group| caps.get(group)).unwrap();
+
+            // Org links without `file:` can point at headings, URLs etc., so
+            // only explicitly relative ones are considered files
+            let is_file = if ext == "org" {
+                caps.get(1).is_some()
+                    || target.as_str().starts_with("./")
+                    || target.as_str().starts_with("../")
+            } else {
+                true
+            };
+
+            let target_str = target.as_str().split("::").next().unwrap_or_default();
+
+            if !is_file || !is_local(target_str) {
+                return link.as_str().to_owned();
+            }
+
+            match f(target_str) {
+                Some(mut new_target) => {
+                    // Markdown targets can't contain spaces, unless they're
+                    // wrapped in `<>`
+                    if ext == "md" && caps.get(1).is_none() && new_target.contains(' ') {
+                        new_target = format!("<{}>", new_target);
+                    }
+
+                    let start = target.start() - link.start();
+                    let end = start + target_str.len();
+
+                    format!(
+                        "{}{}{}",
+                        &link.as_str()[..start],
+                        new_target,
+                        &link.as_str()[end..]
+                    )
+                }
+
+                None => link.as_str().to_owned(),
+            }
+        })
+        .into_owned()
+}
+
+fn is_local(target: &str) -> bool {
+    !target.is_empty()
+        && !target.contains("://")
+        && !target.starts_with(['/', '#', '~'])
+        && !target.starts_with("mailto:")
+}
+
+/// Returns path leading from `dir` to `path` (both relative to the same root),
+/// e.g. `../../media/2018/01/02/photo.jpg`.
+pub fn relative_path(dir: &Path, path: &Path) -> PathBuf {
+    let dir: Vec<_> = dir
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let path: Vec<_> = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+
+    let common = dir.iter().zip(&path).take_while(|(a, b)| a == b).count();
+
+    let mut rel = PathBuf::new();
+
+    for _ in common..dir.len() {
+        rel.push("..");
+    }
+
+    for component in &path[common..] {
+        rel.push(component);
+    }
+
+    rel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("[[file:photo.jpg]]", "org", &["photo.jpg"])]
+    #[test_case("[[file:photo.jpg][Photo]] [[./a/b.png]]", "org", &["photo.jpg", "./a/b.png"])]
+    #[test_case("[[file:photo.jpg::12]]", "org", &["photo.jpg"] ; "search option")]
+    #[test_case("[[https://example.com]] [[Heading]] [[file:/etc/hosts]]", "org", &[] ; "non-local")]
+    #[test_case("![Photo](photo.jpg) [doc](./doc.pdf \"Doc\")", "md", &["photo.jpg", "./doc.pdf"])]
+    #[test_case("![Photo](<my photo.jpg> \"t\")", "md", &["my photo.jpg"] ; "md angle brackets")]
+    #[test_case("[site](https://example.com) [top](#top)", "md", &[] ; "md non-local")]
+    #[test_case("[[file:photo.jpg]] [[file:photo.jpg]]", "org", &["photo.jpg"] ; "duplicate")]
+    #[test_case("[[file:photo.jpg]]", "txt", &[] ; "plain text")]
+    fn find(contents: &str, ext: &str, expected: &[&str]) {
+        assert_eq!(expected, find_note_links(contents, ext));
+    }
+
+    #[test_case(
+        "See [[file:photo.jpg][Photo]] and [[file:other.jpg]].",
+        "org",
+        "See [[file:12-00-00 photo.jpg][Photo]] and [[file:other.jpg]]."
+    )]
+    #[test_case("[[file:photo.jpg::12]]", "org", "[[file:12-00-00 photo.jpg::12]]" ; "search option")]
+    #[test_case("![x](photo.jpg \"t\")", "md", "![x](<12-00-00 photo.jpg> \"t\")")]
+    #[test_case("![x](<photo.jpg>)", "md", "![x](<12-00-00 photo.jpg>)" ; "md angle brackets")]
+    fn rewrite(contents: &str, ext: &str, expected: &str) {
+        let links = vec![("photo.jpg".to_owned(), "12-00-00 photo.jpg".to_owned())];
+
+        assert_eq!(expected, rewrite_note_links(contents, ext, &links));
+    }
+
+    #[test_case("2018/01/02", "2018/01/02/photo.jpg", "photo.jpg")]
+    #[test_case(
+        "journal/2018/01/02",
+        "media/2018/01/02/photo.jpg",
+        "../../../../media/2018/01/02/photo.jpg"
+    )]
+    #[test_case("", "photo.jpg", "photo.jpg" ; "flat")]
+    fn relative(dir: &str, path: &str, expected: &str) {
+        assert_eq!(
+            Path::new(expected),
+            relative_path(Path::new(dir), Path::new(path))
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+
+/// Formats given number of bytes as a human-readable string, e.g. `1.5 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "B";
+
+    for u in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+
+        size /= 1024.0;
+        unit = u;
+    }
+
+    format!("{:.1} {}", size, unit)
+}
+
+/// Parses a human-readable size, e.g. `500MB` or `1.5 GiB`.
+///
+/// Units are binary, i.e. `1 KB` is the same as `1 KiB` (1024 bytes).
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let idx = s
+        .find(|ch: char| ch.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(idx);
+
+    let value: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid size: {}", s))?;
+
+    let multiplier: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        "t" | "tb" | "tib" => 1024 * 1024 * 1024 * 1024,
+        _ => return Err(anyhow!("invalid size unit `{}`: {}", unit, s)),
+    };
+
+    if value < 0.0 {
+        return Err(anyhow!("invalid size: {}", s));
+    }
+
+    Ok((value * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    #[test_case(0, "0 B")]
+    #[test_case(1023, "1023 B")]
+    #[test_case(1024, "1.0 KiB")]
+    #[test_case(1536, "1.5 KiB")]
+    #[test_case(5 * 1024 * 1024 * 1024, "5.0 GiB")]
+    fn format_size(given: u64, expected: &str) {
+        assert_eq!(expected, super::format_size(given));
+    }
+
+    #[test_case("1024", 1024)]
+    #[test_case("10B", 10)]
+    #[test_case("500MB", 500 * 1024 * 1024)]
+    #[test_case("1.5 KiB", 1536)]
+    #[test_case("2g", 2 * 1024 * 1024 * 1024)]
+    fn parse_size(given: &str, expected: u64) {
+        assert_eq!(expected, super::parse_size(given).unwrap());
+    }
+
+    #[test_case("")]
+    #[test_case("MB")]
+    #[test_case("10 parsecs")]
+    #[test_case("-5MB")]
+    fn parse_size_invalid(given: &str) {
+        assert!(super::parse_size(given).is_err());
+    }
+}
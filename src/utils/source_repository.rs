@@ -1,76 +1,389 @@
-use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
-use glob::glob;
+use crate::utils::{is_animated, FileKind, TimeShift};
+use anyhow::{anyhow, Context, Error, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use clap::ValueEnum;
+use glob::Pattern;
 use itertools::Itertools;
-use std::cmp;
+use rayon::iter::Either;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+use std::{cmp, fmt, fs};
+use tracing::{debug, field};
 
 #[derive(Debug)]
 pub struct SourceRepository {
     dir: PathBuf,
+    pattern: Option<String>,
+    exif_cache: Option<PathBuf>,
+    jobs: usize,
+    timestamp_source: TimestampSource,
+    missing_timestamp: MissingTimestamp,
+    excludes: Vec<Pattern>,
+    follow_symlinks: bool,
+    include_hidden: bool,
+    require_exiftool: bool,
+    date_range: Option<RangeInclusive<NaiveDate>>,
+    time_shift: Option<TimeShift>,
+    exiftool_missing: AtomicBool,
 }
 
 impl SourceRepository {
+    /// Opens given source directory; `~` gets expanded and paths containing
+    /// glob metacharacters (e.g. `~/Pictures/2018-*`) are treated as
+    /// patterns, with each matching directory (or file) being imported.
     pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
         let dir = dir.as_ref();
 
-        if !dir.try_exists()? {
-            return Err(anyhow!("source directory not found: {}", dir.display()));
-        }
+        let dir = match dir.to_str() {
+            Some(dir) => PathBuf::from(&*shellexpand::tilde(dir)),
+            None => dir.to_owned(),
+        };
+
+        let pattern = dir
+            .to_str()
+            .filter(|dir| dir.contains(['*', '?', '[']))
+            .map(|dir| dir.to_owned());
+
+        let dir = if let Some(pattern) = &pattern {
+            let matches = glob::glob(pattern)
+                .with_context(|| format!("invalid source pattern: {}", pattern))?;
+
+            if matches.count() == 0 {
+                return Err(anyhow!("source pattern matches nothing: {}", pattern));
+            }
+
+            glob_base(Path::new(pattern))
+        } else {
+            if !dir.try_exists()? {
+                return Err(anyhow!("source directory not found: {}", dir.display()));
+            }
+
+            dir
+        };
 
         Ok(Self {
-            dir: dir.to_owned(),
+            dir,
+            pattern,
+            exif_cache: None,
+            jobs: 0,
+            timestamp_source: TimestampSource::default(),
+            missing_timestamp: MissingTimestamp::default(),
+            excludes: Vec::new(),
+            follow_symlinks: false,
+            include_hidden: false,
+            require_exiftool: false,
+            date_range: None,
+            time_shift: None,
+            exiftool_missing: AtomicBool::new(false),
         })
     }
 
+    pub fn with_exif_cache(mut self, path: impl AsRef<Path>) -> Self {
+        self.exif_cache = Some(path.as_ref().to_owned());
+        self
+    }
+
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    pub fn with_timestamp_source(mut self, timestamp_source: TimestampSource) -> Self {
+        self.timestamp_source = timestamp_source;
+        self
+    }
+
+    /// What to do with files which have neither creation nor
+    /// modification time (as it happens on some network filesystems), when
+    /// their date has to be taken from the file timestamp.
+    pub fn with_missing_timestamp(mut self, missing_timestamp: MissingTimestamp) -> Self {
+        self.missing_timestamp = missing_timestamp;
+        self
+    }
+
+    /// Skips files whose paths (relative to the source directory) match any of
+    /// given patterns.
+    pub fn with_excludes(mut self, excludes: Vec<Pattern>) -> Self {
+        self.excludes = excludes;
+        self
+    }
+
+    /// Media dates (from names or metadata) outside of given range are
+    /// considered bogus and get replaced with the file's timestamp.
+    pub fn with_date_range(mut self, date_range: RangeInclusive<NaiveDate>) -> Self {
+        self.date_range = Some(date_range);
+        self
+    }
+
+    /// Corrects dates of files captured with a miscalibrated clock; notes
+    /// without a time are left as they are.
+    pub fn with_time_shift(mut self, time_shift: TimeShift) -> Self {
+        self.time_shift = Some(time_shift);
+        self
+    }
+
     pub fn iter(&self) -> Result<impl Iterator<Item = Result<FoundSourceFile>>> {
-        let files = glob(&format!("{}/**/*", self.dir.display()))?
-            .filter_ok(|entry| entry.is_file())
-            .map(|entry| {
-                let path = entry?.to_path_buf();
+        if self.require_exiftool {
+            Command::new("exiftool")
+                .arg("-ver")
+                .output()
+                .context("couldn't launch exiftool (required by --require-exiftool)")?;
+        }
 
-                let Some(stem) = path.file_stem() else {
-                    return Ok(FoundSourceFile::Unrecognized(path));
-                };
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
 
-                let Some(ext) = path.extension() else {
-                    return Ok(FoundSourceFile::Unrecognized(path));
-                };
+        if let Some(pattern) = &self.pattern {
+            let mut visited = HashSet::new();
+
+            for entry in glob::glob(pattern)? {
+                let entry = entry?;
+
+                if !self.include_hidden && is_hidden(&entry) {
+                    continue;
+                }
+
+                if entry.is_dir() {
+                    self.walk(&entry, &mut visited, &mut entries, &mut errors)?;
+                } else if entry.is_file() {
+                    entries.push(entry);
+                }
+            }
+        } else {
+            self.walk(&self.dir, &mut HashSet::new(), &mut entries, &mut errors)?;
+        }
+
+        // Sidecars are not imported on their own - they only provide metadata
+        // for the files they accompany
+        let sidecars = find_sidecars(&entries);
+
+        entries.retain(|entry| !self.is_excluded(entry) && !sidecars.contains(entry));
+
+        let pool = ThreadPoolBuilder::new().num_threads(self.jobs).build()?;
+
+        let files: Vec<_> = pool.install(|| -> Result<_> {
+            let cache = ExifCache::prefetch(
+                entries.iter(),
+                self.exif_cache.as_deref(),
+                self.require_exiftool,
+            )?;
+
+            let files = entries
+                .into_par_iter()
+                .map(|entry| self.identify(entry, &cache))
+                .collect();
+
+            if cache.is_exiftool_missing() {
+                self.exiftool_missing.store(true, Ordering::Relaxed);
+            }
+
+            cache.save()?;
+
+            Ok(files)
+        })?;
+
+        Ok(errors.into_iter().map(Err).chain(files))
+    }
+
+    /// Collects all files from given directory, recursively.
+    ///
+    /// (we don't use `glob` here, since it skips paths that aren't valid
+    /// unicode - and we'd like to report those.)
+    ///
+    /// Hidden entries - files and directories whose names start with `.`,
+    /// anywhere below the source directory - are skipped, unless
+    /// `--include-hidden` is active.
+    ///
+    /// Symlinked directories are visited only with `--follow-symlinks`; to
+    /// avoid looping forever on cycles (e.g. `a/link -> a`), each directory is
+    /// visited at most once, as determined by its canonical path.
+    ///
+    /// Subdirectories (and entries) that can't be read don't abort the walk -
+    /// they land in `errors` instead, so that e.g. a single protected folder
+    /// doesn't prevent importing the rest.
+    fn walk(
+        &self,
+        dir: &Path,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+        errors: &mut Vec<Error>,
+    ) -> Result<()> {
+        let canonical_dir = fs::canonicalize(dir)
+            .with_context(|| format!("couldn't resolve: {}", dir.display()))?;
+
+        if !visited.insert(canonical_dir) {
+            return Ok(());
+        }
+
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("couldn't read directory: {}", dir.display()))?
+        {
+            match entry {
+                Ok(entry) => entries.push(entry.path()),
+
+                Err(err) => errors.push(
+                    Error::new(err).context(format!("couldn't read directory: {}", dir.display())),
+                ),
+            }
+        }
+
+        entries.sort();
+
+        for entry in entries {
+            if !self.include_hidden && is_hidden(&entry) {
+                continue;
+            }
+
+            let is_symlink = entry.is_symlink();
+
+            if entry.is_dir() {
+                if !is_symlink || self.follow_symlinks {
+                    if let Err(err) = self.walk(&entry, visited, files, errors) {
+                        errors.push(err);
+                    }
+                }
+            } else if entry.is_file() {
+                files.push(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Fails when exiftool is not installed, instead of falling back to file
+    /// timestamps for media it'd have to read.
+    pub fn with_require_exiftool(mut self, require_exiftool: bool) -> Self {
+        self.require_exiftool = require_exiftool;
+        self
+    }
+
+    /// Returns whether the last `iter()` had to fall back to file timestamps,
+    /// because exiftool is not installed.
+    pub fn is_exiftool_missing(&self) -> bool {
+        self.exiftool_missing.load(Ordering::Relaxed)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let Ok(path) = path.strip_prefix(&self.dir) else {
+            return false;
+        };
+
+        self.excludes
+            .iter()
+            .any(|pattern| pattern.matches_path(path))
+    }
+
+    fn identify(&self, path: PathBuf, cache: &ExifCache) -> Result<FoundSourceFile> {
+        let Some(stem) = path.file_stem() else {
+            return Ok(FoundSourceFile::Unrecognized(path));
+        };
+
+        let Some(ext) = path.extension() else {
+            return Ok(FoundSourceFile::Unrecognized(path));
+        };
+
+        let (Some(stem), Some(ext)) = (stem.to_str(), ext.to_str()) else {
+            return Ok(FoundSourceFile::NonUnicode(path));
+        };
+
+        let file: Result<_> = try {
+            let stem = stem.to_owned();
+            let ext = ext.to_lowercase();
+            let ty = SourceFileType::new(
+                &path,
+                &stem,
+                &ext,
+                cache,
+                self.timestamp_source,
+                self.missing_timestamp,
+            )?;
 
-                let file: Result<_> = try {
-                    let stem = stem
-                        .to_str()
-                        .context("file has non-unicode stem")?
-                        .to_owned();
+            match ty {
+                Some((mut ty, mut date_source)) => {
+                    let mut rejected_date = None;
 
-                    let ext = ext
-                        .to_str()
-                        .context("file has non-unicode extension")?
-                        .to_lowercase();
+                    if let (Some(date_range), Some(date)) = (&self.date_range, ty.datetime_mut()) {
+                        if !date_source.is_timestamp() && !date_range.contains(&date.date()) {
+                            rejected_date = Some(*date);
+
+                            (*date, date_source) = file_timestamp(
+                                &path,
+                                self.timestamp_source,
+                                self.missing_timestamp,
+                            )?;
+                        }
+                    }
+
+                    if let Some(time_shift) = &self.time_shift {
+                        if date_source != DateSource::CurrentTime {
+                            ty.shift(time_shift);
+                        }
+                    }
 
-                    let ty = SourceFileType::new(&path, &stem, &ext)?;
+                    let rel_dir = path
+                        .parent()
+                        .and_then(|dir| dir.strip_prefix(&self.dir).ok())
+                        .map(Path::to_owned)
+                        .unwrap_or_default();
 
-                    ty.map(|ty| SourceFile {
+                    Some(SourceFile {
                         path: path.clone(),
+                        rel_dir,
                         stem,
                         ext,
                         ty,
+                        date_source,
+                        rejected_date,
                     })
-                };
+                }
+
+                None => None,
+            }
+        };
 
-                let file =
-                    file.with_context(|| format!("couldn't identify file: {}", path.display()))?;
+        let file = file.with_context(|| format!("couldn't identify file: {}", path.display()))?;
 
-                if let Some(file) = file {
-                    Ok(FoundSourceFile::Recognized(file))
-                } else {
-                    Ok(FoundSourceFile::Unrecognized(path))
-                }
-            });
+        if let Some(file) = file {
+            debug!(
+                path = %path.display(),
+                kind = ?file.ty.kind(),
+                date = %file.ty.date(),
+                time = file.ty.datetime().map(|dt| field::display(dt.time())),
+                date_source = %file.date_source,
+                rejected_date = file.rejected_date.map(field::display),
+                "recognized file",
+            );
+
+            Ok(FoundSourceFile::Recognized(file))
+        } else {
+            debug!(path = %path.display(), "unrecognized file");
 
-        Ok(files)
+            Ok(FoundSourceFile::Unrecognized(path))
+        }
     }
 }
 
@@ -78,20 +391,97 @@ impl SourceRepository {
 pub enum FoundSourceFile {
     Recognized(SourceFile),
     Unrecognized(PathBuf),
+    NonUnicode(PathBuf),
 }
 
 #[derive(Debug)]
 pub struct SourceFile {
     pub path: PathBuf,
+
+    /// Directory the file has been found in, relative to the source's root
+    /// (empty for files lying straight in the root)
+    pub rel_dir: PathBuf,
+
     pub stem: String,
     pub ext: String,
     pub ty: SourceFileType,
+    pub date_source: DateSource,
+
+    /// Date that was found in file's name or metadata, but which got rejected
+    /// for being out of range (see `with_date_range()`)
+    pub rejected_date: Option<NaiveDateTime>,
+}
+
+/// Which file timestamp to use when file's date cannot be determined from its
+/// name or metadata.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TimestampSource {
+    /// Earlier of creation and modification time
+    #[default]
+    Min,
+
+    /// Creation time
+    Created,
+
+    /// Modification time
+    Modified,
+
+    /// Later of creation and modification time
+    Max,
+}
+
+/// What to do with files that have neither creation nor modification time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MissingTimestamp {
+    /// Don't import the file (it's reported as one that couldn't be
+    /// identified)
+    #[default]
+    Skip,
+
+    /// Use the current time
+    Now,
+}
+
+/// Where file's date comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateSource {
+    FileName,
+    Metadata(&'static str),
+    Sidecar(&'static str),
+    FileTimestamp,
+
+    /// File has no timestamps, so the current time got used instead (see
+    /// [`MissingTimestamp::Now`])
+    CurrentTime,
+}
+
+impl DateSource {
+    /// Whether the date comes from the file system (rather than file's name or
+    /// contents).
+    pub fn is_timestamp(&self) -> bool {
+        matches!(self, Self::FileTimestamp | Self::CurrentTime)
+    }
+}
+
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateSource::FileName => write!(f, "file name"),
+            DateSource::Metadata(tag) => write!(f, "metadata `{}`", tag),
+            DateSource::Sidecar(tag) => write!(f, "sidecar `{}`", tag),
+            DateSource::FileTimestamp => write!(f, "file timestamp"),
+            DateSource::CurrentTime => write!(f, "current time"),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum SourceFileType {
     Note {
         date: NaiveDate,
+
+        /// Time given through the extended `YYYY-MM-DD-HH-MM` name, if any.
+        time: Option<NaiveTime>,
     },
     Photo {
         date: NaiveDateTime,
@@ -101,143 +491,937 @@ pub enum SourceFileType {
         date: NaiveDateTime,
         id: Option<String>,
     },
+    Audio {
+        date: NaiveDateTime,
+        id: Option<String>,
+    },
 }
 
-impl SourceFileType {
-    fn new(path: &Path, stem: &str, ext: &str) -> Result<Option<Self>> {
-        let created_or_modified_at = || -> Result<_> {
-            let metadata = path.metadata()?;
-
-            let date = match (metadata.created(), metadata.modified()) {
-                (Ok(created_at), Ok(modified_at)) => cmp::min(created_at, modified_at),
-                (Ok(created_at), Err(_)) => created_at,
-                (Err(_), Ok(modified_at)) => modified_at,
-                (Err(_), Err(_)) => {
-                    return Err(anyhow!("cannot determine file timestamp"));
-                }
-            };
+impl SourceFile {
+    /// Returns when the file was last modified, falling back to the Unix epoch
+    /// when that's unknown.
+    pub fn modified_at(&self) -> DateTime<Local> {
+        self.path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::from)
+            .unwrap_or_default()
+    }
+}
 
-            Ok(DateTime::<Local>::from(date).naive_local())
-        };
+impl SourceFileType {
+    fn new(
+        path: &Path,
+        stem: &str,
+        ext: &str,
+        cache: &ExifCache,
+        timestamp_source: TimestampSource,
+        missing_timestamp: MissingTimestamp,
+    ) -> Result<Option<(Self, DateSource)>> {
+        match FileKind::from_ext(ext) {
+            Some(FileKind::Note) => {
+                let (date, time) = parse_note_name(stem)?;
 
-        match ext {
-            "org" => {
-                let mut stem = stem.split('-');
-
-                let year = stem
-                    .next()
-                    .context("invalid name: missing year")?
-                    .parse()
-                    .context("invalid name: invalid year")?;
-
-                let month = stem
-                    .next()
-                    .context("invalid name: missing month")?
-                    .parse()
-                    .context("invalid name: invalid month")?;
-
-                let day = stem
-                    .next()
-                    .context("invalid name: missing day")?
-                    .parse()
-                    .context("invalid name: invalid day")?;
-
-                Ok(Some(Self::Note {
-                    date: NaiveDate::from_ymd_opt(year, month, day)
-                        .context("invalid name: invalid date")?,
-                }))
+                Ok(Some((Self::Note { date, time }, DateSource::FileName)))
             }
 
-            "arw" | "jpg" | "png" | "webp" | "heic" | "mov" | "mp4" | "webm" => {
-                enum Kind {
-                    Photo,
-                    Video,
-                }
+            Some(kind @ (FileKind::Photo | FileKind::Video | FileKind::Audio)) => {
+                let (date, id) = match parse_media_name_parts(stem)? {
+                    Some((date, time, id)) => (Some((date, time)), id),
+                    None => (None, None),
+                };
+
+                let (date, date_source) = match date {
+                    Some((date, Some(time))) => (date.and_time(time), DateSource::FileName),
+
+                    // Name carries only the day, so the time comes from the
+                    // metadata - as long as it agrees on the day
+                    Some((date, None)) => match extract_metadata_datetime(path, kind, cache)? {
+                        Some((dt, date_source)) if dt.date() == date => (dt, date_source),
+                        _ => (date.and_time(NaiveTime::MIN), DateSource::FileName),
+                    },
+
+                    None => match extract_metadata_datetime(path, kind, cache)? {
+                        Some(date) => date,
+                        None => file_timestamp(path, timestamp_source, missing_timestamp)?,
+                    },
+                };
+
+                let id = id.or_else(|| stem.strip_prefix("IMG_").map(|id| id.to_owned()));
+
+                // Animated images are closer to short videos than to photos
+                let kind = if kind == FileKind::Photo
+                    && FileKind::is_animatable(ext)
+                    && is_animated(path, ext)
+                {
+                    FileKind::Video
+                } else {
+                    kind
+                };
 
-                let kind = match ext {
-                    "arw" | "jpg" | "png" | "webp" | "heic" => Kind::Photo,
-                    "mov" | "mp4" | "webm" => Kind::Video,
-                    _ => unreachable!(),
+                let ty = match kind {
+                    FileKind::Photo => Self::Photo { date, id },
+                    FileKind::Video => Self::Video { date, id },
+                    FileKind::Audio => Self::Audio { date, id },
+                    FileKind::Note => unreachable!(),
                 };
 
-                let mut date = None;
-                let mut id = None;
+                Ok(Some((ty, date_source)))
+            }
+
+            None => Ok(None),
+        }
+    }
+
+    pub fn kind(&self) -> FileKind {
+        match self {
+            Self::Note { .. } => FileKind::Note,
+            Self::Photo { .. } => FileKind::Photo,
+            Self::Video { .. } => FileKind::Video,
+            Self::Audio { .. } => FileKind::Audio,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            Self::Note { date, .. } => *date,
+            Self::Photo { date, .. } | Self::Video { date, .. } | Self::Audio { date, .. } => {
+                date.date()
+            }
+        }
+    }
+
+    fn datetime_mut(&mut self) -> Option<&mut NaiveDateTime> {
+        match self {
+            Self::Note { .. } => None,
+            Self::Photo { date, .. } | Self::Video { date, .. } | Self::Audio { date, .. } => {
+                Some(date)
+            }
+        }
+    }
+
+    fn shift(&mut self, time_shift: &TimeShift) {
+        match self {
+            Self::Note {
+                date,
+                time: Some(time),
+            } => {
+                let shifted = time_shift.apply(date.and_time(*time));
+
+                *date = shifted.date();
+                *time = shifted.time();
+            }
+
+            Self::Note { time: None, .. } => (),
+
+            Self::Photo { date, .. } | Self::Video { date, .. } | Self::Audio { date, .. } => {
+                *date = time_shift.apply(*date);
+            }
+        }
+    }
+
+    /// Returns file's date and time, or `None` for files which have only a
+    /// date (i.e. notes named just `YYYY-MM-DD`).
+    pub fn datetime(&self) -> Option<NaiveDateTime> {
+        match self {
+            Self::Note { date, time } => time.map(|time| date.and_time(time)),
+            Self::Photo { date, .. } | Self::Video { date, .. } | Self::Audio { date, .. } => {
+                Some(*date)
+            }
+        }
+    }
+}
+
+/// Extracts the date from note names, i.e. `YYYY-MM-DD`, optionally followed
+/// by the time as `-HH-MM`.
+fn parse_note_name(stem: &str) -> Result<(NaiveDate, Option<NaiveTime>)> {
+    let mut stem = stem.split('-');
+
+    let year = stem
+        .next()
+        .context("invalid name: missing year")?
+        .parse()
+        .context("invalid name: invalid year")?;
+
+    let month = stem
+        .next()
+        .context("invalid name: missing month")?
+        .parse()
+        .context("invalid name: invalid month")?;
+
+    let day = stem
+        .next()
+        .context("invalid name: missing day")?
+        .parse()
+        .context("invalid name: invalid day")?;
+
+    let date = NaiveDate::from_ymd_opt(year, month, day).context("invalid name: invalid date")?;
+
+    // Anything that doesn't look like a time is kept ignored, as it was before
+    // times got supported
+    let time = match (stem.next(), stem.next()) {
+        (Some(hour), Some(minute)) if hour.len() == 2 && minute.len() == 2 => {
+            match (hour.parse(), minute.parse()) {
+                (Ok(hour), Ok(minute)) => NaiveTime::from_hms_opt(hour, minute, 0),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    Ok((date, time))
+}
+
+/// Extracts the date (and id, if any) from media names that carry them, e.g.
+/// `2018-01-02_12-34-56_DSC0001`, `IMG-20180102-WA0001` or
+/// `trip_2018-01-02_beach`.
+///
+/// Names without time are assumed to be taken at midnight.
+pub fn parse_media_name(stem: &str) -> Result<Option<(NaiveDateTime, Option<String>)>> {
+    let name = parse_media_name_parts(stem)?;
+
+    Ok(name.map(|(date, time, id)| (date.and_time(time.unwrap_or(NaiveTime::MIN)), id)))
+}
+
+/// Date, time (if the name carries it) and id parsed from a media name.
+type MediaNameParts = (NaiveDate, Option<NaiveTime>, Option<String>);
 
-                if let Some((new_date, new_time, new_id)) = stem.split('_').collect_tuple() {
-                    let new_date = new_date.split('-').collect_tuple();
-                    let new_time = new_time.split('-').collect_tuple();
+/// Like [`parse_media_name()`], but tells apart names that don't carry the
+/// time (e.g. `trip_2018-01-02_beach`), so that it can be taken from elsewhere.
+fn parse_media_name_parts(stem: &str) -> Result<Option<MediaNameParts>> {
+    if let Some((date, id)) = parse_dated_name(stem)? {
+        return Ok(Some((date.date(), Some(date.time()), Some(id))));
+    }
+
+    if let Some((date, id)) = parse_whatsapp_name(stem) {
+        return Ok(Some((date.date(), Some(date.time()), Some(id))));
+    }
+
+    if let Some(date) = parse_android_name(stem) {
+        return Ok(Some((date.date(), Some(date.time()), None)));
+    }
+
+    Ok(parse_embedded_date(stem))
+}
+
+/// Parses names following the `YYYY-MM-DD_HH-MM-SS_id` convention.
+fn parse_dated_name(stem: &str) -> Result<Option<(NaiveDateTime, String)>> {
+    let Some((date, time, id)) = stem.split('_').collect_tuple() else {
+        return Ok(None);
+    };
+
+    let date = date.split('-').collect_tuple();
+    let time = time.split('-').collect_tuple();
+
+    let (Some((year, month, day)), Some((hour, min, sec))) = (date, time) else {
+        return Ok(None);
+    };
+
+    let year = year.parse()?;
+    let month = month.parse()?;
+    let day = day.parse()?;
+
+    let hour = hour.parse()?;
+    let min = min.parse()?;
+    let sec = sec.parse()?;
+
+    let Some(date) = NaiveDate::from_ymd_opt(year, month, day) else {
+        return Ok(None);
+    };
 
-                    if let (Some((year, month, day)), Some((hour, min, sec))) = (new_date, new_time)
-                    {
-                        let year = year.parse()?;
-                        let month = month.parse()?;
-                        let day = day.parse()?;
+    let Some(time) = NaiveTime::from_hms_opt(hour, min, sec) else {
+        return Ok(None);
+    };
+
+    Ok(Some((NaiveDateTime::new(date, time), id.to_string())))
+}
+
+/// Parses names used by WhatsApp, e.g. `IMG-20180102-WA0001`.
+///
+/// WhatsApp doesn't record the time, so it's assumed to be midnight.
+fn parse_whatsapp_name(stem: &str) -> Option<(NaiveDateTime, String)> {
+    let (prefix, date, id) = stem.split('-').collect_tuple()?;
+
+    if !matches!(prefix, "IMG" | "VID" | "AUD" | "PTT") || !id.starts_with("WA") {
+        return None;
+    }
+
+    if date.len() != 8 {
+        return None;
+    }
+
+    let date = NaiveDate::parse_from_str(date, "%Y%m%d").ok()?;
+
+    Some((date.and_time(NaiveTime::MIN), id.to_string()))
+}
+
+/// Parses names used by Android cameras, e.g. `20180102_123456` or
+/// `PXL_20180102_123456789` (where the trailing digits are milliseconds).
+fn parse_android_name(stem: &str) -> Option<NaiveDateTime> {
+    let stem = stem.strip_prefix("PXL_").unwrap_or(stem);
+    let (date, time) = stem.split('_').collect_tuple()?;
 
-                        let hour = hour.parse()?;
-                        let min = min.parse()?;
-                        let sec = sec.parse()?;
+    if date.len() != 8 || time.len() < 6 || !time.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
 
-                        date = Some(NaiveDateTime::new(
-                            NaiveDate::from_ymd_opt(year, month, day).unwrap(),
-                            NaiveTime::from_hms_opt(hour, min, sec).unwrap(),
-                        ));
+    let date = NaiveDate::parse_from_str(date, "%Y%m%d").ok()?;
+    let time = NaiveTime::parse_from_str(&time[0..6], "%H%M%S").ok()?;
 
-                        id = Some(new_id.to_string());
+    Some(date.and_time(time))
+}
+
+/// Finds the first `YYYY-MM-DD` (optionally followed by `HH-MM-SS` or macOS'
+/// ` at HH.MM.SS`) anywhere in the name, e.g. `trip_2018-01-02_beach`; the rest
+/// of the name (`trip_beach`) becomes the id.
+fn parse_embedded_date(stem: &str) -> Option<MediaNameParts> {
+    static REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?:^|\D)((\d{4})-(\d{2})-(\d{2})(?:(?:[_ T-]| at )(\d{2})[-.](\d{2})[-.](\d{2}))?)(?:\D|$)")
+            .unwrap()
+    });
+
+    let caps = REGEX.captures(stem)?;
+    let num = |idx: usize| -> Option<u32> { caps.get(idx)?.as_str().parse().ok() };
+
+    let date = NaiveDate::from_ymd_opt(num(2)? as i32, num(3)?, num(4)?)?;
+
+    let time = match (num(5), num(6), num(7)) {
+        (Some(hour), Some(min), Some(sec)) => Some(NaiveTime::from_hms_opt(hour, min, sec)?),
+        _ => None,
+    };
+
+    let found = caps.get(1)?;
+    let separators: &[char] = &['_', '-', ' ', '.'];
+
+    let id = [
+        stem[..found.start()].trim_end_matches(separators),
+        stem[found.end()..].trim_start_matches(separators),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .join("_");
+
+    Some((date, time, (!id.is_empty()).then_some(id)))
+}
+
+fn exiftool_tag(kind: FileKind) -> Option<&'static str> {
+    match kind {
+        FileKind::Photo => Some("-DateTimeOriginal"),
+        FileKind::Video => Some("-MediaCreateDate"),
+        FileKind::Audio => Some("-CreateDate"),
+        FileKind::Note => None,
+    }
+}
+
+/// Media timestamps extracted ahead of time, so that identifying files
+/// doesn't require spawning exiftool once per file.
+#[derive(Debug, Default)]
+struct ExifCache {
+    dates: HashMap<PathBuf, Option<NaiveDateTime>>,
+
+    /// Timestamps of files that had to be probed one-by-one (e.g. because the
+    /// batch got discarded), so that they can be persisted as well
+    probed: Mutex<HashMap<PathBuf, Option<NaiveDateTime>>>,
+
+    /// Where to persist the timestamps, if anywhere (see [`Self::save()`])
+    store: Option<(PathBuf, ExifStore)>,
+
+    require_exiftool: bool,
+
+    /// Set once exiftool turns out not to be installed, so that we don't keep
+    /// trying to launch it
+    exiftool_missing: AtomicBool,
+}
+
+impl ExifCache {
+    /// Extracts timestamps of all media files that don't carry their date in
+    /// the name, reading photos in-process and passing the rest to a single
+    /// exiftool invocation.
+    ///
+    /// When exiftool's output can't be aligned with the input files, the batch
+    /// is discarded and those files fall back to being probed one-by-one.
+    ///
+    /// If `store` is given, timestamps are reused from there for as long as
+    /// the file's mtime doesn't change, and persisted back by [`Self::save()`].
+    fn prefetch<'a>(
+        paths: impl Iterator<Item = &'a PathBuf> + Send,
+        store: Option<&Path>,
+        require_exiftool: bool,
+    ) -> Result<Self> {
+        let stored = store.map(ExifStore::load).transpose()?;
+
+        let (mut dates, pending): (HashMap<_, _>, Vec<_>) = paths
+            .par_bridge()
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?;
+                let ext = path.extension()?.to_str()?;
+                let kind = FileKind::from_ext(&ext.to_lowercase())?;
+                let tag = exiftool_tag(kind)?;
+
+                // (names without time still need the metadata for it)
+                if !matches!(parse_media_name_parts(stem), Ok(None | Some((_, None, _)))) {
+                    return None;
+                }
+
+                if let Some(date) = stored.as_ref().and_then(|stored| stored.get(path)) {
+                    return Some(Either::Left((path.to_owned(), date)));
+                }
+
+                if kind == FileKind::Photo {
+                    if let Some(date) = extract_exif_datetime(path) {
+                        return Some(Either::Left((path.to_owned(), date)));
                     }
                 }
 
-                let tag = match kind {
-                    Kind::Photo => "-DateTimeOriginal",
-                    Kind::Video => "-MediaCreateDate",
-                };
+                Some(Either::Right((path.to_owned(), tag)))
+            })
+            .partition_map(|entry| entry);
 
-                let date = if let Some(date) = date {
-                    date
-                } else if let Some(date) = extract_media_datetime(path, tag)? {
-                    date
-                } else {
-                    created_or_modified_at()?
-                };
+        // Split the batch so that each worker gets its own exiftool process
+        let chunk_size = pending.len().div_ceil(rayon::current_num_threads()).max(1);
 
-                let id = id.or_else(|| stem.strip_prefix("IMG_").map(|id| id.to_owned()));
+        let batches: Vec<_> = pending
+            .par_chunks(chunk_size)
+            .filter_map(Self::run_exiftool)
+            .collect();
+
+        dates.extend(batches.into_iter().flatten());
 
-                Ok(Some(match kind {
-                    Kind::Photo => Self::Photo { date, id },
-                    Kind::Video => Self::Video { date, id },
-                }))
+        Ok(Self {
+            dates,
+            probed: Default::default(),
+            store: store.map(Path::to_owned).zip(stored),
+            require_exiftool,
+            exiftool_missing: AtomicBool::new(false),
+        })
+    }
+
+    fn run_exiftool(
+        files: &[(PathBuf, &'static str)],
+    ) -> Option<Vec<(PathBuf, Option<NaiveDateTime>)>> {
+        if files.is_empty() {
+            return None;
+        }
+
+        let mut child = Command::new("exiftool")
+            .arg("-q")
+            .arg("-s")
+            .arg("-T")
+            .args(EXIFTOOL_BATCH_TAGS)
+            .arg("-@")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        {
+            let mut stdin = child.stdin.take()?;
+
+            for (path, _) in files {
+                writeln!(stdin, "{}", path.display()).ok()?;
             }
+        }
+
+        let out = child.wait_with_output().ok()?.stdout;
+        let tags: Vec<_> = files.iter().map(|(_, tag)| *tag).collect();
+        let dates = parse_exiftool_batch(&String::from_utf8_lossy(&out), &tags)?;
+
+        Some(
+            files
+                .iter()
+                .map(|(path, _)| path.to_owned())
+                .zip(dates)
+                .collect(),
+        )
+    }
 
-            _ => Ok(None),
+    /// Persists all known timestamps - prefetched and probed one-by-one -
+    /// into the store given to [`Self::prefetch()`], if any.
+    fn save(self) -> Result<()> {
+        let Some((path, mut store)) = self.store else {
+            return Ok(());
+        };
+
+        let probed = self.probed.into_inner().unwrap();
+
+        for (file, date) in self.dates.iter().chain(&probed) {
+            store.insert(file, *date);
         }
+
+        store.save(&path)
     }
 
-    pub fn date(&self) -> NaiveDate {
-        match self {
-            Self::Note { date } => *date,
-            Self::Photo { date, .. } | Self::Video { date, .. } => date.date(),
+    fn get(&self, path: &Path) -> Option<Option<NaiveDateTime>> {
+        self.dates.get(path).copied()
+    }
+
+    fn insert_probed(&self, path: &Path, date: Option<NaiveDateTime>) {
+        self.probed.lock().unwrap().insert(path.to_owned(), date);
+    }
+
+    fn is_exiftool_missing(&self) -> bool {
+        self.exiftool_missing.load(Ordering::Relaxed)
+    }
+}
+
+/// Tags printed by the batched exiftool invocation, in this order.
+const EXIFTOOL_BATCH_TAGS: [&str; 3] = ["-DateTimeOriginal", "-MediaCreateDate", "-CreateDate"];
+
+/// Parses output of `exiftool -s -T` run with [`EXIFTOOL_BATCH_TAGS`] - a line
+/// per file, with tab-separated values of all the tags - picking given tag for
+/// each file; returns `None` if the output doesn't line up with the files.
+fn parse_exiftool_batch(out: &str, tags: &[&str]) -> Option<Vec<Option<NaiveDateTime>>> {
+    let lines: Vec<_> = out.lines().collect();
+
+    if lines.len() != tags.len() {
+        return None;
+    }
+
+    tags.iter()
+        .zip(lines)
+        .map(|(tag, line)| {
+            let idx = EXIFTOOL_BATCH_TAGS.iter().position(|t| t == tag)?;
+            let out = line.split('\t').nth(idx)?.trim();
+
+            if out == "-" || out == "0000:00:00 00:00:00" {
+                Some(None)
+            } else {
+                Some(Some(parse_exiftool_date(out)?))
+            }
+        })
+        .collect()
+}
+
+/// On-disk counterpart of [`ExifCache`], keyed by absolute path and
+/// invalidated whenever the file's mtime changes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExifStore {
+    entries: HashMap<PathBuf, ExifStoreEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExifStoreEntry {
+    mtime: SystemTime,
+    date: Option<NaiveDateTime>,
+}
+
+impl ExifStore {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.try_exists()? {
+            return Ok(Self::default());
+        }
+
+        let store = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read exif cache: {}", path.display()))?;
+
+        // A corrupted cache is not worth failing the import over - it'll get
+        // rebuilt from scratch
+        Ok(serde_json::from_str(&store).unwrap_or_default())
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            if !dir.as_os_str().is_empty() && !dir.try_exists()? {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("couldn't create directory: {}", dir.display()))?;
+            }
+        }
+
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("couldn't write exif cache: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    fn get(&self, path: &Path) -> Option<Option<NaiveDateTime>> {
+        let entry = self.entries.get(&std::path::absolute(path).ok()?)?;
+
+        if entry.mtime == path.metadata().ok()?.modified().ok()? {
+            Some(entry.date)
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, path: &Path, date: Option<NaiveDateTime>) {
+        let Ok(key) = std::path::absolute(path) else {
+            return;
+        };
+
+        let Some(mtime) = path.metadata().and_then(|m| m.modified()).ok() else {
+            return;
+        };
+
+        self.entries.insert(key, ExifStoreEntry { mtime, date });
+    }
+}
+
+/// Tags read from `.xmp` sidecars, in the order of preference.
+const XMP_TAGS: &[(&str, &str)] = &[
+    ("exif:DateTimeOriginal", "DateTimeOriginal"),
+    ("xmp:CreateDate", "CreateDate"),
+    ("photoshop:DateCreated", "DateCreated"),
+];
+
+/// Returns which of given files are sidecars of other media files among them -
+/// either `.xmp` (`IMG.xmp` or `IMG.ARW.xmp`) or Google Takeout's `.json`
+/// (`IMG.jpg.json` or `IMG.jpg.supplemental-metadata.json`).
+///
+/// `.xmp` and `.json` files without a matching media file are not sidecars,
+/// so they get reported like any other unrecognized file.
+fn find_sidecars(paths: &[PathBuf]) -> HashSet<PathBuf> {
+    let is_media = |path: &Path| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| FileKind::from_ext(&ext.to_lowercase()))
+            .is_some_and(|kind| kind != FileKind::Note)
+    };
+
+    let media: HashSet<_> = paths.iter().filter(|path| is_media(path)).collect();
+    let media_stems: HashSet<_> = media.iter().map(|path| path.with_extension("")).collect();
+
+    paths
+        .iter()
+        .filter(|path| {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                return false;
+            };
+
+            let ext = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+
+            match ext.as_deref() {
+                Some("xmp") => {
+                    let base = path.with_extension("");
+
+                    media.contains(&base) || media_stems.contains(&base)
+                }
+
+                Some("json") => {
+                    let base = [".supplemental-metadata.json", ".json"]
+                        .into_iter()
+                        .find_map(|suffix| name.strip_suffix(suffix))
+                        .unwrap_or(name);
+
+                    media.contains(&path.with_file_name(base))
+                }
+
+                _ => false,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns the part of given glob pattern preceding its first component with
+/// metacharacters, e.g. `photos` for `photos/2018-*/*.jpg`.
+fn glob_base(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+}
+
+/// Reads capture time from file's `.xmp` sidecar (either `IMG.xmp` or
+/// `IMG.ARW.xmp`) or Google Takeout's `.json` sidecar, if there's one.
+/// Extracts when given media file was taken from its sidecar or, if there's
+/// none, from the file's own metadata.
+fn extract_metadata_datetime(
+    path: &Path,
+    kind: FileKind,
+    cache: &ExifCache,
+) -> Result<Option<(NaiveDateTime, DateSource)>> {
+    if let Some((date, tag)) = extract_sidecar_datetime(path)? {
+        return Ok(Some((date, DateSource::Sidecar(tag))));
+    }
+
+    if let Some(date) = extract_media_datetime(path, kind, cache)? {
+        let tag = exiftool_tag(kind)
+            .unwrap_or_default()
+            .trim_start_matches('-');
+
+        return Ok(Some((date, DateSource::Metadata(tag))));
+    }
+
+    Ok(None)
+}
+
+fn extract_sidecar_datetime(path: &Path) -> Result<Option<(NaiveDateTime, &'static str)>> {
+    if let Some(date) = extract_xmp_datetime(path)? {
+        return Ok(Some(date));
+    }
+
+    if let Some(date) = extract_takeout_datetime(path)? {
+        return Ok(Some((date, "photoTakenTime")));
+    }
+
+    Ok(None)
+}
+
+fn extract_xmp_datetime(path: &Path) -> Result<Option<(NaiveDateTime, &'static str)>> {
+    let candidates = [
+        Some(path.with_extension("xmp")),
+        path.file_name().map(|name| {
+            let mut name = name.to_owned();
+            name.push(".xmp");
+            path.with_file_name(name)
+        }),
+    ];
+
+    for sidecar in candidates.into_iter().flatten() {
+        if !sidecar.try_exists()? {
+            continue;
+        }
+
+        let xmp = fs::read_to_string(&sidecar)
+            .with_context(|| format!("couldn't read: {}", sidecar.display()))?;
+
+        for (tag, name) in XMP_TAGS {
+            let Some(value) = find_xmp_value(&xmp, tag) else {
+                continue;
+            };
+
+            let date = parse_xmp_date(value)
+                .with_context(|| format!("couldn't parse `{}` in: {}", tag, sidecar.display()))?;
+
+            return Ok(Some((date, name)));
         }
     }
+
+    Ok(None)
 }
 
-fn extract_media_datetime(path: &Path, tag: &str) -> Result<Option<NaiveDateTime>> {
+/// Reads `photoTakenTime` from Google Takeout's sidecar - `IMG.jpg.json` or,
+/// in newer exports, `IMG.jpg.supplemental-metadata.json`.
+fn extract_takeout_datetime(path: &Path) -> Result<Option<NaiveDateTime>> {
+    let Some(name) = path.file_name() else {
+        return Ok(None);
+    };
+
+    for suffix in [".json", ".supplemental-metadata.json"] {
+        let mut sidecar = name.to_owned();
+
+        sidecar.push(suffix);
+
+        let sidecar = path.with_file_name(sidecar);
+
+        if !sidecar.try_exists()? {
+            continue;
+        }
+
+        let json = fs::read_to_string(&sidecar)
+            .with_context(|| format!("couldn't read: {}", sidecar.display()))?;
+
+        return parse_takeout_date(&json)
+            .with_context(|| format!("couldn't parse: {}", sidecar.display()));
+    }
+
+    Ok(None)
+}
+
+/// Parses `photoTakenTime.timestamp` (unix seconds, as a string) out of
+/// Google Takeout's sidecar.
+fn parse_takeout_date(json: &str) -> Result<Option<NaiveDateTime>> {
+    let json: serde_json::Value = serde_json::from_str(json)?;
+
+    let timestamp = match &json["photoTakenTime"]["timestamp"] {
+        serde_json::Value::Null => return Ok(None),
+        serde_json::Value::String(timestamp) => timestamp.parse().ok(),
+        timestamp => timestamp.as_i64(),
+    };
+
+    let date = timestamp
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .context("invalid `photoTakenTime`")?;
+
+    Ok(Some(date.with_timezone(&Local).naive_local()))
+}
+
+/// Finds value of given XMP property, stored either as an attribute
+/// (`tag="value"`) or as an element (`<tag>value</tag>`).
+fn find_xmp_value<'a>(xmp: &'a str, tag: &str) -> Option<&'a str> {
+    let attr = format!("{}=\"", tag);
+
+    if let Some(idx) = xmp.find(&attr) {
+        return xmp[idx + attr.len()..].split('"').next();
+    }
+
+    let elem = format!("<{}>", tag);
+    let idx = xmp.find(&elem)?;
+
+    xmp[idx + elem.len()..].split('<').next()
+}
+
+/// Parses XMP's date (`2018-01-02T12:34:56+01:00`, with optional seconds,
+/// fraction and offset).
+fn parse_xmp_date(s: &str) -> Option<NaiveDateTime> {
+    let (d, t) = s.trim().split_once('T')?;
+    let d = d.replace('-', ":");
+
+    let t = if t.get(5..6) == Some(":") {
+        t.to_owned()
+    } else {
+        format!("{}:00{}", t.get(..5)?, &t[5..])
+    };
+
+    parse_exiftool_date(&format!("{} {}", d, t))
+}
+
+/// Returns file's creation or modification time, as selected by
+/// `timestamp_source`.
+fn file_timestamp(
+    path: &Path,
+    timestamp_source: TimestampSource,
+    missing_timestamp: MissingTimestamp,
+) -> Result<(NaiveDateTime, DateSource)> {
+    let metadata = path.metadata()?;
+
+    let date = pick_timestamp(metadata.created(), metadata.modified(), timestamp_source);
+
+    match (date, missing_timestamp) {
+        (Some(date), _) => Ok((
+            DateTime::<Local>::from(date).naive_local(),
+            DateSource::FileTimestamp,
+        )),
+
+        (None, MissingTimestamp::Skip) => Err(anyhow!(
+            "cannot determine file timestamp (use `--on-missing-timestamp now` to import it anyway)"
+        )),
+
+        (None, MissingTimestamp::Now) => Ok((Local::now().naive_local(), DateSource::CurrentTime)),
+    }
+}
+
+/// Picks creation or modification time, as selected by `timestamp_source`,
+/// falling back to whichever is available.
+fn pick_timestamp(
+    created_at: io::Result<SystemTime>,
+    modified_at: io::Result<SystemTime>,
+    timestamp_source: TimestampSource,
+) -> Option<SystemTime> {
+    match (created_at, modified_at) {
+        (Ok(created_at), Ok(modified_at)) => Some(match timestamp_source {
+            TimestampSource::Min => cmp::min(created_at, modified_at),
+            TimestampSource::Created => created_at,
+            TimestampSource::Modified => modified_at,
+            TimestampSource::Max => cmp::max(created_at, modified_at),
+        }),
+        (Ok(created_at), Err(_)) => Some(created_at),
+        (Err(_), Ok(modified_at)) => Some(modified_at),
+        (Err(_), Err(_)) => None,
+    }
+}
+
+/// Reads capture time of given photo, video or audio file from its metadata
+/// (e.g. EXIF), skipping the scanner's cache.
+pub fn read_media_datetime(path: &Path, kind: FileKind) -> Result<Option<NaiveDateTime>> {
+    extract_media_datetime(path, kind, &ExifCache::default())
+}
+
+fn extract_media_datetime(
+    path: &Path,
+    kind: FileKind,
+    cache: &ExifCache,
+) -> Result<Option<NaiveDateTime>> {
+    if let Some(date) = cache.get(path) {
+        return Ok(date);
+    }
+
+    if kind == FileKind::Photo {
+        if let Some(date) = extract_exif_datetime(path) {
+            cache.insert_probed(path, date);
+
+            return Ok(date);
+        }
+    }
+
+    let Some(tag) = exiftool_tag(kind) else {
+        return Ok(None);
+    };
+
+    if cache.is_exiftool_missing() {
+        return Ok(None);
+    }
+
     let out = Command::new("exiftool")
         .arg("-s")
         .arg("-T")
         .arg(tag)
         .arg(path)
-        .output()
-        .context("couldn't launch exiftool")?
-        .stdout;
+        .output();
+
+    let out = match out {
+        Ok(out) => out.stdout,
+
+        // Without exiftool the caller falls back to file's timestamp, which is
+        // better than refusing to import anything at all
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !cache.require_exiftool => {
+            cache.exiftool_missing.store(true, Ordering::Relaxed);
+
+            return Ok(None);
+        }
+
+        Err(err) => {
+            return Err(err).context("couldn't launch exiftool");
+        }
+    };
 
     let out = String::from_utf8_lossy(&out);
     let out = out.trim();
 
-    if out == "-" || out == "0000:00:00 00:00:00" {
-        Ok(None)
+    let date = if out == "-" || out == "0000:00:00 00:00:00" {
+        None
     } else {
-        parse_exiftool_date(out)
-            .map(Some)
-            .with_context(|| format!("couldn't parse exiftool's response: {}", out))
+        Some(
+            parse_exiftool_date(out)
+                .with_context(|| format!("couldn't parse exiftool's response: {}", out))?,
+        )
+    };
+
+    cache.insert_probed(path, date);
+
+    Ok(date)
+}
+
+/// Reads `DateTimeOriginal` in-process, without spawning exiftool.
+///
+/// Returns `None` when the file's format is not supported by the embedded
+/// reader, in which case the caller should fall back to exiftool.
+fn extract_exif_datetime(path: &Path) -> Option<Option<NaiveDateTime>> {
+    let file = File::open(path).ok()?;
+
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    let Some(field) = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY) else {
+        return Some(None);
+    };
+
+    let exif::Value::Ascii(values) = &field.value else {
+        return Some(None);
+    };
+
+    let value = String::from_utf8_lossy(values.first()?);
+    let value = value.trim();
+
+    if value.is_empty() || value == "0000:00:00 00:00:00" {
+        Some(None)
+    } else {
+        parse_exiftool_date(value).map(Some)
     }
 }
 
@@ -254,38 +1438,75 @@ fn parse_exiftool_date(s: &str) -> Option<NaiveDateTime> {
     }
 
     let (d, t) = s.split(' ').collect_tuple()?;
-    let t = t.split_once('-').map(|(t, _)| t).unwrap_or(t);
-    let (d_y, d_m, d_d) = d.split(':').collect_tuple()?;
-    let (t_h, t_m, t_s) = t.split(':').collect_tuple()?;
 
-    let t_s = if let Some(ms_idx) = t_s.find('.') {
-        &t_s[0..ms_idx]
-    } else {
-        t_s
+    // Time is followed by optional fractional seconds (`.123`) and optional
+    // offset (`Z`, `+02:00` or `-05:30`)
+    let tail_idx = t.find(['.', 'Z', '+', '-']).unwrap_or(t.len());
+    let (t, tail) = t.split_at(tail_idx);
+
+    let tail = match tail.strip_prefix('.') {
+        Some(tail) => tail.trim_start_matches(|ch: char| ch.is_ascii_digit()),
+        None => tail,
     };
 
-    let t_s = if let Some(sep_idx) = t_s.find('-') {
-        &t_s[0..sep_idx]
-    } else {
-        t_s
+    let offset = match tail {
+        "" => None,
+        "Z" => Some(0),
+
+        tail => {
+            let (sign, tail) = tail.split_at(1);
+            let (o_h, o_m) = tail.split(':').collect_tuple()?;
+            let offset = parse::<i32>(o_h)? * 3600 + parse::<i32>(o_m)? * 60;
+
+            match sign {
+                "+" => Some(offset),
+                "-" => Some(-offset),
+                _ => return None,
+            }
+        }
     };
 
+    let (d_y, d_m, d_d) = d.split(':').collect_tuple()?;
+    let (t_h, t_m, t_s) = t.split(':').collect_tuple()?;
+
     let dt = NaiveDateTime::new(
         NaiveDate::from_ymd_opt(parse(d_y)?, parse(d_m)?, parse(d_d)?)?,
         NaiveTime::from_hms_opt(parse(t_h)?, parse(t_m)?, parse(t_s)?)?,
     );
 
+    if let Some(offset) = offset {
+        // Timestamp carries its own offset - use it to find the actual instant
+        // and then convert it into our local time
+        return Some(
+            FixedOffset::east_opt(offset)?
+                .from_local_datetime(&dt)
+                .single()?
+                .with_timezone(&Local)
+                .naive_local(),
+        );
+    }
+
     Some(dt + Local.offset_from_utc_date(&dt.date()))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use super::{ExifStore, TimestampSource};
+    use filetime::FileTime;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+    use std::{env, fs, io, process};
     use test_case::test_case;
 
     #[test_case("2016:04:23 20:19:55", "2016-04-23 20:19:55")]
     #[test_case("2016:04:23 20:19:55.1234", "2016-04-23 20:19:55")]
-    #[test_case("2016:04:23 20:19:55-20:19", "2016-04-23 20:19:55")]
+    #[test_case("2016:04:23 20:19:55+02:00", "2016-04-23 18:19:55")]
+    #[test_case("2016:04:23 20:19:55-05:00", "2016-04-24 01:19:55")]
+    #[test_case("2016:04:23 20:19:55.1234+02:00", "2016-04-23 18:19:55")]
+    #[test_case("2016:04:23 20:19:55+05:30", "2016-04-23 14:49:55")]
+    #[test_case("2016:04:23 20:19:55Z", "2016-04-23 20:19:55")]
+    #[test_case("2016:04:23 20:19:55.123Z", "2016-04-23 20:19:55")]
+    #[test_case("2016:04:23 20:19:55.123-05:00", "2016-04-24 01:19:55")]
     fn parse_exiftool_date(given: &str, expected: &str) {
         env::set_var("TZ", "UTC");
 
@@ -293,4 +1514,251 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test_case(
+        "2016:04:23 20:19:55\t-\t-\n-\t2017:01:02 03:04:05\t-\n",
+        &["-DateTimeOriginal", "-MediaCreateDate"],
+        Some(vec![Some("2016-04-23 20:19:55"), Some("2017-01-02 03:04:05")])
+        ; "aligned"
+    )]
+    #[test_case(
+        "-\t-\t-\n0000:00:00 00:00:00\t-\t-\n",
+        &["-DateTimeOriginal", "-DateTimeOriginal"],
+        Some(vec![None, None])
+        ; "missing"
+    )]
+    #[test_case(
+        "2016:04:23 20:19:55\t-\t-\n",
+        &["-DateTimeOriginal", "-DateTimeOriginal"],
+        None
+        ; "short"
+    )]
+    #[test_case(
+        "2016:04:23 20:19:55\n",
+        &["-CreateDate"],
+        None
+        ; "short line"
+    )]
+    #[test_case(
+        "yesterday\t-\t-\n",
+        &["-DateTimeOriginal"],
+        None
+        ; "invalid"
+    )]
+    fn parse_exiftool_batch(out: &str, tags: &[&str], expected: Option<Vec<Option<&str>>>) {
+        env::set_var("TZ", "UTC");
+
+        let actual = super::parse_exiftool_batch(out, tags).map(|dates| {
+            dates
+                .into_iter()
+                .map(|date| date.map(|date| date.to_string()))
+                .collect::<Vec<_>>()
+        });
+
+        let expected = expected.map(|dates| {
+            dates
+                .into_iter()
+                .map(|date| date.map(str::to_owned))
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(expected, actual);
+    }
+
+    /// Creates an empty directory for given test within the temporary one.
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("diary-{}-{}", test, process::id()));
+
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn exif_store_round_trip() {
+        let dir = temp_dir("exif-store-round-trip");
+        let store_path = dir.join("cache").join("exif.json");
+        let photo = dir.join("IMG_0001.jpg");
+        let video = dir.join("VID_0001.mp4");
+        let date = "2018-01-02T12:34:56".parse().unwrap();
+
+        fs::write(&photo, "photo").unwrap();
+        fs::write(&video, "video").unwrap();
+
+        let mut store = ExifStore::load(&store_path).unwrap();
+
+        assert_eq!(None, store.get(&photo));
+
+        store.insert(&photo, Some(date));
+        store.insert(&video, None);
+        store.save(&store_path).unwrap();
+
+        let store = ExifStore::load(&store_path).unwrap();
+
+        assert_eq!(Some(Some(date)), store.get(&photo));
+        assert_eq!(Some(None), store.get(&video));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn exif_store_invalidation() {
+        let dir = temp_dir("exif-store-invalidation");
+        let photo = dir.join("IMG_0001.jpg");
+        let date = "2018-01-02T12:34:56".parse().unwrap();
+
+        fs::write(&photo, "photo").unwrap();
+        filetime::set_file_mtime(&photo, FileTime::from_unix_time(1_500_000_000, 0)).unwrap();
+
+        let mut store = ExifStore::default();
+
+        store.insert(&photo, Some(date));
+
+        assert_eq!(Some(Some(date)), store.get(&photo));
+
+        // File has been changed since, so its date has to be read again
+        filetime::set_file_mtime(&photo, FileTime::from_unix_time(1_600_000_000, 0)).unwrap();
+
+        assert_eq!(None, store.get(&photo));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test_case("2018-01-02", Some("2018-01-02 (none)"))]
+    #[test_case("2018-01-02-12-34", Some("2018-01-02 12:34:00"))]
+    #[test_case("2018-01-02-trip", Some("2018-01-02 (none)") ; "suffix")]
+    #[test_case("2018-01-02-25-00", Some("2018-01-02 (none)") ; "invalid time")]
+    #[test_case("2018-01-02-1-2", Some("2018-01-02 (none)") ; "short time")]
+    #[test_case("2018-01-32", None ; "invalid date")]
+    #[test_case("notes", None)]
+    fn parse_note_name(given: &str, expected: Option<&str>) {
+        let actual = super::parse_note_name(given).ok().map(|(date, time)| {
+            let time = time.map_or("(none)".to_owned(), |time| time.to_string());
+
+            format!("{} {}", date, time)
+        });
+
+        assert_eq!(expected, actual.as_deref());
+    }
+
+    #[test_case("photos/*", "photos")]
+    #[test_case("/home/me/photos/2018-*/*.jpg", "/home/me/photos")]
+    #[test_case("photos/IMG_?.jpg", "photos")]
+    #[test_case("[ab]/photos", "")]
+    fn glob_base(given: &str, expected: &str) {
+        assert_eq!(Path::new(expected), super::glob_base(Path::new(given)));
+    }
+
+    #[test_case(Some(1), Some(2), TimestampSource::Min, Some(1))]
+    #[test_case(Some(1), Some(2), TimestampSource::Max, Some(2))]
+    #[test_case(Some(1), Some(2), TimestampSource::Created, Some(1) ; "created")]
+    #[test_case(Some(1), Some(2), TimestampSource::Modified, Some(2) ; "modified")]
+    #[test_case(None, Some(2), TimestampSource::Created, Some(2) ; "missing created")]
+    #[test_case(Some(1), None, TimestampSource::Modified, Some(1) ; "missing modified")]
+    #[test_case(None, None, TimestampSource::Min, None ; "missing both")]
+    fn pick_timestamp(
+        created_at: Option<u64>,
+        modified_at: Option<u64>,
+        timestamp_source: TimestampSource,
+        expected: Option<u64>,
+    ) {
+        let time = |secs: Option<u64>| {
+            secs.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::Unsupported))
+        };
+
+        let actual = super::pick_timestamp(time(created_at), time(modified_at), timestamp_source);
+
+        assert_eq!(time(expected).ok(), actual);
+    }
+
+    #[test_case("2016-04-23T20:19:55", "2016-04-23 20:19:55")]
+    #[test_case("2016-04-23T20:19", "2016-04-23 20:19:00")]
+    #[test_case("2016-04-23T20:19:55.12+02:00", "2016-04-23 18:19:55")]
+    #[test_case("2016-04-23T20:19+02:00", "2016-04-23 18:19:00")]
+    fn parse_xmp_date(given: &str, expected: &str) {
+        env::set_var("TZ", "UTC");
+
+        let actual = super::parse_xmp_date(given).unwrap().to_string();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test_case(
+        r#"<rdf:Description exif:DateTimeOriginal="2016-04-23T20:19:55"/>"#,
+        "exif:DateTimeOriginal",
+        Some("2016-04-23T20:19:55")
+    )]
+    #[test_case(
+        "<xmp:CreateDate>2016-04-23T20:19</xmp:CreateDate>",
+        "xmp:CreateDate",
+        Some("2016-04-23T20:19")
+    )]
+    #[test_case(
+        "<xmp:CreateDate>2016-04-23T20:19</xmp:CreateDate>",
+        "exif:DateTimeOriginal",
+        None
+    )]
+    fn find_xmp_value(xmp: &str, tag: &str, expected: Option<&str>) {
+        assert_eq!(expected, super::find_xmp_value(xmp, tag));
+    }
+
+    #[test_case(
+        r#"{"title": "IMG_0001.jpg", "photoTakenTime": {"timestamp": "1514896496", "formatted": "2 Jan 2018, 12:34:56 UTC"}}"#,
+        Some("2018-01-02 12:34:56")
+        ; "string timestamp"
+    )]
+    #[test_case(
+        r#"{"photoTakenTime": {"timestamp": 1514896496}}"#,
+        Some("2018-01-02 12:34:56")
+        ; "numeric timestamp"
+    )]
+    #[test_case(r#"{"creationTime": {"timestamp": "1514896496"}}"#, None ; "missing")]
+    fn parse_takeout_date(json: &str, expected: Option<&str>) {
+        env::set_var("TZ", "UTC");
+
+        let actual = super::parse_takeout_date(json)
+            .unwrap()
+            .map(|date| date.to_string());
+
+        assert_eq!(expected.map(|date| date.to_string()), actual);
+    }
+
+    #[test_case("not json")]
+    #[test_case(r#"{"photoTakenTime": {"timestamp": "yesterday"}}"#)]
+    fn parse_takeout_date_invalid(json: &str) {
+        assert!(super::parse_takeout_date(json).is_err());
+    }
+
+    #[test_case("2018-01-02_12-34-56_DSC0001", Some(("2018-01-02 12:34:56", Some("DSC0001"))))]
+    #[test_case("IMG-20180102-WA0001", Some(("2018-01-02 00:00:00", Some("WA0001"))))]
+    #[test_case("VID-20180102-WA0002", Some(("2018-01-02 00:00:00", Some("WA0002"))))]
+    #[test_case("20180102_123456", Some(("2018-01-02 12:34:56", None)))]
+    #[test_case("PXL_20180102_123456789", Some(("2018-01-02 12:34:56", None)))]
+    #[test_case("PXL_20180102_1234", None)]
+    #[test_case("PXL_2018012_123456", None)]
+    #[test_case("IMG-2018012-WA0001", None)]
+    #[test_case("IMG-20180102-0001", None)]
+    #[test_case("IMG_0001", None)]
+    #[test_case("2018-01-02_beach", Some(("2018-01-02 00:00:00", Some("beach"))))]
+    #[test_case("trip_2018-01-02_beach", Some(("2018-01-02 00:00:00", Some("trip_beach"))))]
+    #[test_case("trip_2018-01-02", Some(("2018-01-02 00:00:00", Some("trip"))))]
+    #[test_case("trip 2018-01-02 12-34-56", Some(("2018-01-02 12:34:56", Some("trip"))))]
+    #[test_case("trip_2018-01-02_12-34-56_beach", Some(("2018-01-02 12:34:56", Some("trip_beach"))))]
+    #[test_case("2018-01-02", Some(("2018-01-02 00:00:00", None)))]
+    #[test_case("Screenshot 2018-01-02 at 12.34.56", Some(("2018-01-02 12:34:56", Some("Screenshot"))))]
+    #[test_case("trip_2018-13-02_beach", None)]
+    #[test_case("trip_12018-01-02", None)]
+    fn parse_media_name(given: &str, expected: Option<(&str, Option<&str>)>) {
+        let actual = super::parse_media_name(given).unwrap();
+
+        let actual = actual
+            .as_ref()
+            .map(|(dt, id)| (dt.to_string(), id.as_deref()));
+
+        let expected = expected.map(|(dt, id)| (dt.to_string(), id));
+
+        assert_eq!(expected, actual);
+    }
 }
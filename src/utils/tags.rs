@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Result};
+
+/// Parses a tag given through `--tag`.
+///
+/// Tags follow Org mode's rules, i.e. they may consist of letters, digits and
+/// `_`, `@`, `#`, `%`, so that they can be put into `#+FILETAGS` as-is.
+pub fn parse_tag(s: &str) -> Result<String> {
+    let is_valid = !s.is_empty()
+        && s.chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '@' | '#' | '%'));
+
+    if is_valid {
+        Ok(s.to_owned())
+    } else {
+        Err(anyhow!(
+            "invalid tag `{}` (tags may contain only letters, digits and `_@#%`)",
+            s
+        ))
+    }
+}
+
+/// Adds tags into note's `#+FILETAGS: :a:b:` header line, creating the line
+/// (right after other `#+` keywords, such as `#+TITLE`) if it's not present.
+///
+/// Tags already present in the note are left alone.
+pub fn add_note_tags(note: &str, tags: &[String]) -> String {
+    let mut lines: Vec<String> = note.lines().map(|line| line.to_owned()).collect();
+
+    let header_len = lines
+        .iter()
+        .take_while(|line| line.trim_start().starts_with("#+"))
+        .count();
+
+    let filetags = lines[..header_len].iter().position(|line| {
+        line.trim_start()
+            .get(..11)
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("#+FILETAGS:"))
+    });
+
+    match filetags {
+        Some(idx) => {
+            let (keyword, value) = lines[idx].split_at(lines[idx].find(':').unwrap() + 1);
+
+            let mut all_tags: Vec<_> = value
+                .split(':')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| tag.to_owned())
+                .collect();
+
+            merge(&mut all_tags, tags);
+
+            lines[idx] = format!("{} :{}:", keyword, all_tags.join(":"));
+        }
+
+        None => {
+            let mut all_tags = Vec::new();
+
+            merge(&mut all_tags, tags);
+
+            let line = format!("#+FILETAGS: :{}:", all_tags.join(":"));
+
+            if header_len == 0 && lines.first().is_some_and(|line| !line.is_empty()) {
+                lines.insert(0, String::new());
+            }
+
+            lines.insert(header_len, line);
+        }
+    }
+
+    let mut note = lines.join("\n");
+
+    note.push('\n');
+    note
+}
+
+/// Adds tags into a `.tags` sidecar, which lists one tag per line.
+///
+/// Tags already present in the sidecar are left alone.
+pub fn add_sidecar_tags(sidecar: &str, tags: &[String]) -> String {
+    let mut all_tags: Vec<_> = sidecar
+        .lines()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_owned())
+        .collect();
+
+    merge(&mut all_tags, tags);
+
+    let mut sidecar = all_tags.join("\n");
+
+    sidecar.push('\n');
+    sidecar
+}
+
+fn merge(all_tags: &mut Vec<String>, tags: &[String]) {
+    for tag in tags {
+        if !all_tags.contains(tag) {
+            all_tags.push(tag.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("work", true)]
+    #[test_case("work_trip", true)]
+    #[test_case("@home", true)]
+    #[test_case("zażółć", true)]
+    #[test_case("", false)]
+    #[test_case("work trip", false ; "space")]
+    #[test_case("work:trip", false ; "colon")]
+    #[test_case("work-trip", false ; "dash")]
+    fn parse(tag: &str, expected: bool) {
+        assert_eq!(expected, parse_tag(tag).is_ok());
+    }
+
+    #[test_case(
+        "",
+        "#+FILETAGS: :work:travel:\n"
+        ; "empty note"
+    )]
+    #[test_case(
+        "* 2018-01-02\n\nHello!\n",
+        "#+FILETAGS: :work:travel:\n\n* 2018-01-02\n\nHello!\n"
+        ; "note without header"
+    )]
+    #[test_case(
+        "#+TITLE: 2018-01-02\n\nHello!\n",
+        "#+TITLE: 2018-01-02\n#+FILETAGS: :work:travel:\n\nHello!\n"
+        ; "note with title"
+    )]
+    #[test_case(
+        "#+TITLE: 2018-01-02\n#+filetags: :travel:family:\n\nHello!\n",
+        "#+TITLE: 2018-01-02\n#+filetags: :travel:family:work:\n\nHello!\n"
+        ; "note with tags"
+    )]
+    fn note(note: &str, expected: &str) {
+        let tags = ["work".into(), "travel".into()];
+
+        assert_eq!(expected, add_note_tags(note, &tags));
+    }
+
+    #[test_case("", "work\ntravel\n" ; "empty sidecar")]
+    #[test_case("travel\nfamily\n", "travel\nfamily\nwork\n" ; "sidecar with tags")]
+    fn sidecar(sidecar: &str, expected: &str) {
+        let tags = ["work".into(), "travel".into()];
+
+        assert_eq!(expected, add_sidecar_tags(sidecar, &tags));
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use std::ops::RangeInclusive;
+
+/// Correction applied to media dates, for archives captured with a camera
+/// whose clock was off (see `add --time-shift`).
+#[derive(Clone, Debug)]
+pub struct TimeShift {
+    pub delta: TimeDelta,
+
+    /// Only dates (before shifting) within this range get shifted
+    pub range: RangeInclusive<NaiveDate>,
+}
+
+impl TimeShift {
+    pub fn apply(&self, date: NaiveDateTime) -> NaiveDateTime {
+        if self.range.contains(&date.date()) {
+            date.checked_add_signed(self.delta).unwrap_or(date)
+        } else {
+            date
+        }
+    }
+}
+
+/// Parses a shift given through `--time-shift`, e.g. `+03:00:00` or
+/// `-00:15:30`.
+pub fn parse_time_shift(s: &str) -> Result<TimeDelta> {
+    let err = || anyhow!("invalid time shift `{}` (expected `±HH:MM:SS`)", s);
+
+    let (sign, rest) = if let Some(rest) = s.strip_prefix('+') {
+        (1, rest)
+    } else if let Some(rest) = s.strip_prefix('-') {
+        (-1, rest)
+    } else {
+        (1, s)
+    };
+
+    let mut parts = rest.split(':').map(|part| {
+        if part.len() == 2 && part.chars().all(|ch| ch.is_ascii_digit()) {
+            part.parse::<i64>().map_err(|_| err())
+        } else {
+            Err(err())
+        }
+    });
+
+    let (Some(hours), Some(minutes), Some(seconds), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(err());
+    };
+
+    let (hours, minutes, seconds) = (hours?, minutes?, seconds?);
+
+    if minutes >= 60 || seconds >= 60 {
+        return Err(err());
+    }
+
+    Ok(TimeDelta::seconds(
+        sign * (hours * 3600 + minutes * 60 + seconds),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("+03:00:00", Some(3 * 3600))]
+    #[test_case("03:00:00", Some(3 * 3600) ; "no sign")]
+    #[test_case("-00:15:30", Some(-(15 * 60 + 30)))]
+    #[test_case("+48:00:00", Some(48 * 3600) ; "many days")]
+    #[test_case("+3:00:00", None ; "short hours")]
+    #[test_case("+03:00", None ; "missing seconds")]
+    #[test_case("+03:60:00", None ; "invalid minutes")]
+    #[test_case("+03:00:00:00", None ; "too many parts")]
+    fn parse(given: &str, expected: Option<i64>) {
+        let actual = parse_time_shift(given)
+            .ok()
+            .map(|delta| delta.num_seconds());
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test_case("2018-01-02 22:30:00", "2018-01-03 01:30:00" ; "crossing midnight")]
+    #[test_case("2018-01-05 12:00:00", "2018-01-05 15:00:00" ; "last day")]
+    #[test_case("2018-01-06 12:00:00", "2018-01-06 12:00:00" ; "out of range")]
+    fn apply(given: &str, expected: &str) {
+        let shift = TimeShift {
+            delta: TimeDelta::hours(3),
+            range: NaiveDate::from_ymd_opt(2018, 1, 1).unwrap()
+                ..=NaiveDate::from_ymd_opt(2018, 1, 5).unwrap(),
+        };
+
+        let given = NaiveDateTime::parse_from_str(given, "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(expected, shift.apply(given).to_string());
+    }
+}
@@ -1,25 +1,140 @@
 use clap::Parser;
-use diary::{Cmd, Env};
+use diary::{Cmd, Config, Env, Verbosity};
 use dircpy::copy_dir;
 use glob::glob;
 use pretty_assertions as pa;
+use regex::Regex;
+use std::io::Write;
 use std::path::Path;
 use std::{env, fs};
 use test_case::test_case;
 
+#[test_case("add-animated")]
+#[test_case("add-append")]
+#[test_case("add-append-move")]
+#[test_case("add-audio")]
+#[test_case("add-bogus-date")]
+#[test_case("add-burst")]
+#[test_case("add-check-writable")]
 #[test_case("add-complementary-video-1")]
 #[test_case("add-complementary-video-2")]
+#[test_case("add-concurrency-safe")]
+#[test_case("add-config")]
+#[test_case("add-convert-heic")]
+#[test_case("add-convert-heic-remove")]
+#[test_case("add-copy-buffer-size")]
+#[test_case("add-copy-note-assets")]
+#[test_case("add-dedup")]
+#[test_case("add-dedup-nested")]
 #[test_case("add-dry-run")]
+#[test_case("add-dry-run-json")]
+#[test_case("add-duplicate-notes")]
+#[test_case("add-embedded-date")]
+#[test_case("add-events")]
+#[test_case("add-exclude")]
 #[test_case("add-filter-from")]
 #[test_case("add-filter-from-to")]
+#[test_case("add-filter-month")]
 #[test_case("add-filter-on")]
+#[test_case("add-filter-type-note")]
+#[test_case("add-filter-type-photo-video")]
+#[test_case("add-filter-weekday")]
+#[test_case("add-filter-year")]
+#[test_case("add-flatten")]
+#[test_case("add-geojson")]
+#[test_case("add-hardlink")]
+#[test_case("add-hash-manifest")]
+#[test_case("add-hash-manifest-sha256")]
+#[test_case("add-hidden")]
+#[test_case("add-include-hidden")]
+#[test_case("add-jobs")]
+#[test_case("add-journal")]
+#[test_case("add-journal-append")]
+#[test_case("add-journal-failed")]
+#[test_case("add-journal-move")]
+#[test_case("add-keep-structure")]
+#[test_case("add-layout")]
+#[test_case("add-link-latest")]
+#[test_case("add-live-photos")]
+#[test_case("add-max-size")]
+#[test_case("add-merge-notes")]
+#[test_case("add-mixed-notes")]
+#[test_case("add-move")]
+#[test_case("add-multiple-sources")]
+#[test_case("add-name-collision")]
+#[test_case("add-name-template")]
+#[test_case("add-no-group-bursts")]
+#[test_case("add-no-prefix-date")]
+#[test_case("add-normalize-ext")]
+#[test_case("add-normalize-ext-alias")]
+#[test_case("add-note-time")]
+#[test_case("add-on-conflict-fail")]
+#[test_case("add-on-conflict-overwrite")]
+#[test_case("add-on-conflict-overwrite-dry-run")]
+#[test_case("add-on-conflict-skip")]
+#[test_case("add-overwrite")]
+#[test_case("add-prefix-date")]
+#[test_case("add-quarantine")]
+#[test_case("add-quarantine-unconfirmed")]
+#[test_case("add-quiet")]
+#[test_case("add-raw")]
 #[test_case("add-remove")]
 #[test_case("add-remove-and-dry-run")]
+#[test_case("add-remove-unconfirmed")]
+#[test_case("add-report")]
+#[test_case("add-require-changes")]
+#[test_case("add-scan-errors")]
 #[test_case("add-screenshot")]
+#[test_case("add-silent")]
+#[test_case("add-since-last-run")]
 #[test_case("add-smoke")]
+#[test_case("add-sort-by-date")]
+#[test_case("add-source-glob")]
+#[test_case("add-subdirs")]
+#[test_case("add-symlink")]
+#[test_case("add-tag")]
+#[test_case("add-takeout-sidecar")]
+#[test_case("add-thumbnails")]
+#[test_case("add-thumbnails-raw")]
+#[test_case("add-time-shift")]
+#[test_case("add-time-window")]
+#[test_case("add-trash-dir")]
+#[test_case("add-trash-dir-quarantine")]
 #[test_case("add-verbose")]
 #[test_case("add-video")]
 #[test_case("add-where-date-is-in-file-name")]
+#[test_case("add-with-note")]
+#[test_case("add-xmp-sidecar")]
+#[test_case("check-invalid-date")]
+#[test_case("check-problems")]
+#[test_case("check-smoke")]
+#[test_case("dedup-apply")]
+#[test_case("dedup-report")]
+#[test_case("export-smoke")]
+#[test_case("export-zip")]
+#[test_case("export-zip-dry-run")]
+#[test_case("init-smoke")]
+#[test_case("list-burst")]
+#[test_case("list-smoke")]
+#[test_case("list-subdirs")]
+#[test_case("move-conflict")]
+#[test_case("move-smoke")]
+#[test_case("move-tags")]
+#[test_case("prune-dry-run")]
+#[test_case("prune-smoke")]
+#[test_case("rename-dry-run")]
+#[test_case("rename-smoke")]
+#[test_case("search-regex")]
+#[test_case("search-smoke")]
+#[test_case("today-create")]
+#[test_case("today-existing")]
+#[test_case("undo-dry-run")]
+#[test_case("undo-modified")]
+#[test_case("undo-move")]
+#[test_case("undo-smoke")]
+#[test_case("verify-md5")]
+#[test_case("verify-problems")]
+#[test_case("verify-smoke")]
 fn test(case: &str) {
     colored::control::set_override(false);
     env::set_var("TZ", "UTC");
@@ -55,9 +170,22 @@ fn test(case: &str) {
     // ---
 
     let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    let config = given.join("config.toml");
+
+    let config = if config.exists() {
+        Config::load_from(config).unwrap()
+    } else {
+        Config::default()
+    };
 
     let mut env = Env {
         stdout: &mut stdout,
+        stderr: &mut stderr,
+        config,
+        verbosity: Verbosity::default(),
+        is_terminal: false,
     };
 
     let cmd = {
@@ -70,14 +198,31 @@ fn test(case: &str) {
         Cmd::parse_from(cmd.split(' '))
     };
 
-    cmd.run(&mut env).unwrap();
+    if let Err(err) = cmd.run(&mut env) {
+        writeln!(stderr, "Error: {:#}", err).unwrap();
+    }
 
     // ---
 
-    let stdout = String::from_utf8_lossy(&stdout);
-    let stdout = stdout.replace(&format!("tests/acc/{}/.tmp/", case), "");
+    // Elapsed time and modification times differ between runs
+    let mtime = Regex::new(r"modified \d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}").unwrap();
+
+    let normalize = |out: &[u8]| -> String {
+        String::from_utf8_lossy(out)
+            .replace(&format!("tests/acc/{}/.tmp/", case), "")
+            .lines()
+            .map(|line| {
+                if line.starts_with("  took ") {
+                    "  took (elapsed)\n".to_string()
+                } else {
+                    format!("{}\n", mtime.replace_all(line, "modified (mtime)"))
+                }
+            })
+            .collect()
+    };
 
-    assert_file_eq(expected.join("stdout"), stdout);
+    assert_file_eq(expected.join("stdout"), normalize(&stdout));
+    assert_file_eq(expected.join("stderr"), normalize(&stderr));
     assert_fs_eq(expected_diary, tmp_diary);
     assert_fs_eq(expected_source, tmp_source);
 
@@ -119,7 +264,8 @@ fn assert_fs_eq(expected: impl AsRef<Path>, actual: impl AsRef<Path>) {
                     .display()
                     .to_string()
             })
-            .filter(|entry| entry != ".gitkeep")
+            // (`.gitkeep`s keep otherwise empty directories around in git)
+            .filter(|entry| !entry.ends_with(".gitkeep"))
             .collect();
 
         paths.sort();
@@ -0,0 +1,89 @@
+//! Tests running the actual binary, for things the in-process acceptance tests
+//! can't see - e.g. how logs and warnings share stderr.
+
+use dircpy::copy_dir;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn add_with_debug_logs() {
+    let tmp = Path::new("tests").join(".tmp-cli-debug-logs");
+
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    fs::create_dir_all(tmp.join("diary")).unwrap();
+    copy_dir("tests/acc/add-smoke/given/source", tmp.join("source")).unwrap();
+
+    // Logs are written from the scanner's worker threads, so they mustn't wait
+    // for stderr held by the main thread
+    let mut child = Command::new(env!("CARGO_BIN_EXE_diary"))
+        .args(["add", "--dry-run", "--diary"])
+        .arg(tmp.join("diary"))
+        .arg("--source")
+        .arg(tmp.join("source"))
+        .env("RUST_LOG", "diary=debug")
+        .stdout(Stdio::null())
+        .stderr(File::create(tmp.join("stderr")).unwrap())
+        .spawn()
+        .unwrap();
+
+    let started_at = Instant::now();
+
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+
+        if started_at.elapsed() > Duration::from_secs(30) {
+            child.kill().unwrap();
+            panic!("diary add got stuck");
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    assert!(status.success());
+    assert!(fs::read_to_string(tmp.join("stderr"))
+        .unwrap()
+        .contains("recognized file"));
+
+    fs::remove_dir_all(&tmp).unwrap();
+}
+
+#[test]
+fn help_with_broken_config() {
+    let tmp = Path::new("tests").join(".tmp-cli-broken-config");
+
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    fs::create_dir_all(&tmp).unwrap();
+    fs::write(tmp.join("diary.toml"), "bogus = 1\n").unwrap();
+
+    // The config is loaded only after the command line got parsed
+    let status = Command::new(env!("CARGO_BIN_EXE_diary"))
+        .arg("--help")
+        .current_dir(&tmp)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+
+    let status = Command::new(env!("CARGO_BIN_EXE_diary"))
+        .args(["init", "--diary", "diary"])
+        .current_dir(&tmp)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+
+    fs::remove_dir_all(&tmp).unwrap();
+}